@@ -0,0 +1,319 @@
+//! Parsing into a bump arena, for documents that are read once and dropped.
+
+use crate::{Number, ParseError, Value};
+use bumpalo::collections::Vec as BumpVec;
+use bumpalo::Bump;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+use std::ops;
+
+/// A JSON value allocated in a `Bump`. Arrays and objects are plain slices, so nothing is shared
+/// or reference counted and the whole tree is freed at once when the arena is dropped or reset.
+/// Object entries are sorted by key, with the last of any duplicate keys kept.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArenaValue<'a> {
+    Null,
+    /// Never a `Number::Big`, which owns its text: the arena does not run destructors, so that
+    /// text would leak. Such numbers are stored as `BigNumber` instead.
+    Number(Number),
+    /// The exact text of a number that only `Number::Big` can hold.
+    #[cfg(feature = "arbitrary_precision")]
+    BigNumber(&'a str),
+    String(&'a str),
+    Bool(bool),
+    Array(&'a [ArenaValue<'a>]),
+    Object(&'a [(&'a str, ArenaValue<'a>)]),
+}
+
+const NULL: ArenaValue<'static> = ArenaValue::Null;
+
+impl<'a> ArenaValue<'a> {
+    pub fn is_null(&self) -> bool {
+        match self {
+            ArenaValue::Null => true,
+            _ => false,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            ArenaValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            ArenaValue::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        self.as_number().and_then(Number::as_i64)
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        self.as_number().and_then(Number::as_u64)
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        self.as_number().and_then(Number::as_f64)
+    }
+
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self {
+            ArenaValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&'a [ArenaValue<'a>]> {
+        match self {
+            ArenaValue::Array(arr) => Some(arr),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&'a [(&'a str, ArenaValue<'a>)]> {
+        match self {
+            ArenaValue::Object(entries) => Some(entries),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` in an object by binary search.
+    pub fn get(&self, key: &str) -> Option<&'a ArenaValue<'a>> {
+        let entries = self.as_object()?;
+        entries
+            .binary_search_by(|(k, _)| (*k).cmp(key))
+            .ok()
+            .map(|idx| &entries[idx].1)
+    }
+
+    pub fn get_index(&self, idx: usize) -> Option<&'a ArenaValue<'a>> {
+        self.as_array()?.get(idx)
+    }
+
+    /// Resolves a JSON pointer, as `Value::pointer` does.
+    pub fn pointer(&self, pointer: &str) -> Option<&ArenaValue<'a>> {
+        if pointer.is_empty() {
+            return Some(self);
+        }
+        if !pointer.starts_with('/') {
+            return None;
+        }
+        let mut target = self;
+        for token in pointer[1..].split('/') {
+            let token = token.replace("~1", "/").replace("~0", "~");
+            target = match target {
+                ArenaValue::Object(_) => target.get(&token)?,
+                ArenaValue::Array(_) => target.get_index(token.parse().ok()?)?,
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Copies the tree out of the arena into a persistent `Value`.
+    pub fn to_value(&self) -> Value {
+        match self {
+            ArenaValue::Null => Value::Null,
+            ArenaValue::Number(n) => Value::Number(n.clone()),
+            #[cfg(feature = "arbitrary_precision")]
+            ArenaValue::BigNumber(s) => Value::Number(Number::Big((*s).to_owned())),
+            ArenaValue::String(s) => Value::String((*s).to_owned()),
+            ArenaValue::Bool(b) => Value::Bool(*b),
+            ArenaValue::Array(arr) => Value::from_array_iter(arr.iter().map(ArenaValue::to_value)),
+            ArenaValue::Object(entries) => {
+                let mut obj = crate::Object::new();
+                for (key, value) in entries.iter() {
+                    obj.keys.push_back((*key).to_owned());
+                    obj.values.push_back(value.to_value());
+                }
+                Value::Object(obj)
+            }
+        }
+    }
+}
+
+impl<'a> ops::Index<&str> for ArenaValue<'a> {
+    type Output = ArenaValue<'a>;
+    fn index(&self, key: &str) -> &ArenaValue<'a> {
+        self.get(key).unwrap_or(&NULL)
+    }
+}
+
+impl<'a> ops::Index<usize> for ArenaValue<'a> {
+    type Output = ArenaValue<'a>;
+    fn index(&self, idx: usize) -> &ArenaValue<'a> {
+        self.get_index(idx).unwrap_or(&NULL)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ValueSeed<'a>(&'a Bump);
+
+impl<'de, 'a> DeserializeSeed<'de> for ValueSeed<'a> {
+    type Value = ArenaValue<'a>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for ValueSeed<'a> {
+    type Value = ArenaValue<'a>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(ArenaValue::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Self::Value, E> {
+        Ok(ArenaValue::Bool(b))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Self::Value, E> {
+        Ok(ArenaValue::Number(n.into()))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Self::Value, E> {
+        Ok(ArenaValue::Number(n.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Self::Value, E> {
+        Ok(ArenaValue::Number(Number::Float(n)))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(ArenaValue::String(self.0.alloc_str(s)))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut items = BumpVec::new_in(self.0);
+        while let Some(item) = seq.next_element_seed(self)? {
+            items.push(item);
+        }
+        Ok(ArenaValue::Array(items.into_bump_slice()))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut next_key = map.next_key_seed(KeySeed(self.0))?;
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            if next_key == Some(crate::NUMBER_TOKEN) {
+                let text = map.next_value::<String>()?;
+                return Ok(match crate::number_from_token::<A::Error>(&text)? {
+                    Number::Big(big) => ArenaValue::BigNumber(self.0.alloc_str(&big)),
+                    n => ArenaValue::Number(n),
+                });
+            }
+        }
+        let mut entries = BumpVec::new_in(self.0);
+        while let Some(key) = next_key {
+            entries.push((key, map.next_value_seed(self)?));
+            next_key = map.next_key_seed(KeySeed(self.0))?;
+        }
+        // Reversing first lets the stable sort and dedup keep the last of any duplicate keys.
+        entries.reverse();
+        entries.sort_by(|a: &(&str, ArenaValue), b| a.0.cmp(b.0));
+        entries.dedup_by(|a, b| a.0 == b.0);
+        Ok(ArenaValue::Object(entries.into_bump_slice()))
+    }
+}
+
+struct KeySeed<'a>(&'a Bump);
+
+impl<'de, 'a> DeserializeSeed<'de> for KeySeed<'a> {
+    type Value = &'a str;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de, 'a> Visitor<'de> for KeySeed<'a> {
+    type Value = &'a str;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an object key")
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(self.0.alloc_str(s))
+    }
+}
+
+impl Value {
+    /// Parses a JSON document into `arena` instead of building persistent vectors. This is faster
+    /// for documents that are read and then dropped, but the result cannot be shared or modified
+    /// and lives only as long as the arena.
+    pub fn from_str_arena<'a>(s: &str, arena: &'a Bump) -> Result<ArenaValue<'a>, ParseError> {
+        let mut deserializer = serde_json::Deserializer::from_str(s);
+        let value = ValueSeed(arena).deserialize(&mut deserializer)?;
+        deserializer.end()?;
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn reads_fields_from_arena() {
+        let arena = Bump::new();
+        let body = r#"{"user": {"name": "ada", "id": 7}, "tags": ["a", "b"], "ok": true}"#;
+        let value = Value::from_str_arena(body, &arena).unwrap();
+
+        assert_eq!(value["user"]["name"].as_str(), Some("ada"));
+        assert_eq!(value["user"]["id"].as_i64(), Some(7));
+        assert_eq!(value["tags"][1].as_str(), Some("b"));
+        assert_eq!(
+            value.pointer("/ok").and_then(ArenaValue::as_bool),
+            Some(true)
+        );
+        assert!(value["missing"].is_null());
+        assert_eq!(value.as_object().map(|entries| entries.len()), Some(3));
+
+        let expected: Value =
+            json!({"user": {"name": "ada", "id": 7}, "tags": ["a", "b"], "ok": true}).into();
+        assert_eq!(value.to_value(), expected);
+    }
+
+    #[test]
+    fn keeps_last_duplicate_key() {
+        let arena = Bump::new();
+        let value = Value::from_str_arena(r#"{"b": 1, "a": 2, "b": 3}"#, &arena).unwrap();
+        assert_eq!(value["b"].as_i64(), Some(3));
+        let keys: Vec<&str> = value.as_object().unwrap().iter().map(|(k, _)| *k).collect();
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn reads_numbers_with_arbitrary_precision() {
+        let arena = Bump::new();
+        let value = Value::from_str_arena(r#"{"a": 1, "b": [2.5, 1e400]}"#, &arena).unwrap();
+        assert_eq!(value["a"].as_i64(), Some(1));
+        assert_eq!(value["b"][0].as_f64(), Some(2.5));
+        assert_eq!(value["b"][1], ArenaValue::BigNumber("1e400"));
+        assert_eq!(value["b"][1].as_number(), None);
+        assert_eq!(
+            value.to_value(),
+            Value::from_str_borrowed(r#"{"a": 1, "b": [2.5, 1e400]}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_input() {
+        let arena = Bump::new();
+        assert!(Value::from_str_arena("[1] 2", &arena).is_err());
+        assert!(Value::from_str_arena("{\"a\":", &arena).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{ParseError, Value};
+use serde_json::Value as JsonValue;
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+impl Value {
+    /// Reads `reader` to the end and parses its contents. Only the read is asynchronous: parsing is
+    /// CPU-bound and happens on the calling task once all of the input has arrived.
+    pub async fn from_async_reader<R>(mut reader: R) -> Result<Value, ParseError>
+    where
+        R: AsyncRead + Unpin,
+    {
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf).await?;
+        Ok(serde_json::from_slice::<JsonValue>(&buf)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Cursor;
+
+    #[tokio::test]
+    async fn parses_from_async_reader() {
+        let reader = Cursor::new(br#"{"a": [1, 2], "b": "c"}"#.to_vec());
+        let value = Value::from_async_reader(reader).await.unwrap();
+        let expected: Value = json!({"a": [1, 2], "b": "c"}).into();
+        assert_eq!(value, expected);
+
+        let reader = Cursor::new(b"[1, 2".to_vec());
+        assert!(matches!(
+            Value::from_async_reader(reader).await,
+            Err(ParseError::Syntax(_))
+        ));
+    }
+}
@@ -0,0 +1,41 @@
+//! Binary data carried in string values as standard base64 with padding.
+
+use crate::Value;
+use ::base64::engine::general_purpose::STANDARD;
+use ::base64::Engine;
+
+impl Value {
+    /// Decodes a string value as standard base64. Returns `None` for other values and for strings
+    /// that are not valid base64.
+    pub fn as_base64_bytes(&self) -> Option<Vec<u8>> {
+        STANDARD.decode(self.as_str()?).ok()
+    }
+
+    pub fn from_bytes_base64(bytes: &[u8]) -> Value {
+        Value::String(STANDARD.encode(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_bytes() {
+        let samples: [&[u8]; 5] = [b"", b"f", b"fo", b"foo", &[0, 255, 128, 7]];
+        for bytes in &samples {
+            let value = Value::from_bytes_base64(bytes);
+            assert_eq!(value.as_base64_bytes().as_deref(), Some(*bytes));
+        }
+        assert_eq!(Value::from_bytes_base64(b"foo"), Value::from("Zm9v"));
+        assert_eq!(Value::from("Zm8=").as_base64_bytes(), Some(b"fo".to_vec()));
+    }
+
+    #[test]
+    fn rejects_invalid_base64() {
+        assert_eq!(Value::from("not base64!").as_base64_bytes(), None);
+        assert_eq!(Value::from("Zm8").as_base64_bytes(), None);
+        assert_eq!(Value::Number(1.into()).as_base64_bytes(), None);
+        assert_eq!(Value::Null.as_base64_bytes(), None);
+    }
+}
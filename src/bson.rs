@@ -0,0 +1,157 @@
+//! BSON conversion. Integers are stored as `Int32` when they fit and `Int64` otherwise, while
+//! floats are stored as `Double`. A `PosInt` larger than `i64::MAX` has no BSON integer type, and
+//! is rejected rather than silently losing precision as a `Double`. BSON types without a JSON
+//! equivalent, such as `ObjectId` or `DateTime`, are read as their relaxed Extended JSON form.
+
+use crate::{Number, Object, Value};
+use ::bson::{Bson, Document};
+use librrb::Vector;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BsonError {
+    /// An unsigned integer too large for `Int64`.
+    IntegerOutOfRange(u64),
+    /// A `Double` that is infinite or NaN.
+    NonFiniteFloat(f64),
+    /// A `Big` number, which no BSON numeric type holds exactly.
+    #[cfg(feature = "arbitrary_precision")]
+    BigNumber(String),
+}
+
+impl fmt::Display for BsonError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BsonError::IntegerOutOfRange(n) => {
+                write!(formatter, "{} does not fit in a BSON Int64", n)
+            }
+            BsonError::NonFiniteFloat(f) => write!(formatter, "{} is not a finite number", f),
+            #[cfg(feature = "arbitrary_precision")]
+            BsonError::BigNumber(s) => write!(formatter, "{} does not fit in a BSON number", s),
+        }
+    }
+}
+
+impl Error for BsonError {}
+
+fn number_to_bson(n: &Number) -> Result<Bson, BsonError> {
+    match *n {
+        Number::PosInt(u) => {
+            if let Ok(i) = i32::try_from(u) {
+                Ok(Bson::Int32(i))
+            } else if let Ok(i) = i64::try_from(u) {
+                Ok(Bson::Int64(i))
+            } else {
+                Err(BsonError::IntegerOutOfRange(u))
+            }
+        }
+        Number::NegInt(i) => Ok(i32::try_from(i).map_or(Bson::Int64(i), Bson::Int32)),
+        Number::Float(f) => Ok(Bson::Double(f)),
+        #[cfg(feature = "arbitrary_precision")]
+        Number::Big(ref s) => Err(BsonError::BigNumber(s.clone())),
+    }
+}
+
+impl Value {
+    pub fn to_bson(&self) -> Result<Bson, BsonError> {
+        Ok(match self {
+            Value::Null => Bson::Null,
+            Value::Bool(b) => Bson::Boolean(*b),
+            Value::Number(n) => number_to_bson(n)?,
+            Value::String(s) => Bson::String(s.clone()),
+            Value::Array(arr) => {
+                let mut items = Vec::with_capacity(arr.len());
+                for item in arr.iter() {
+                    items.push(item.to_bson()?);
+                }
+                Bson::Array(items)
+            }
+            Value::Object(obj) => {
+                let mut doc = Document::new();
+                for (key, value) in obj.iter() {
+                    doc.insert(key.clone(), value.to_bson()?);
+                }
+                Bson::Document(doc)
+            }
+        })
+    }
+
+    pub fn from_bson(bson: Bson) -> Result<Value, BsonError> {
+        Ok(match bson {
+            Bson::Null => Value::Null,
+            Bson::Boolean(b) => Value::Bool(b),
+            Bson::Int32(i) => Value::Number(i.into()),
+            Bson::Int64(i) => Value::Number(i.into()),
+            Bson::Double(f) => {
+                Value::Number(Number::from_f64(f).ok_or(BsonError::NonFiniteFloat(f))?)
+            }
+            Bson::String(s) => Value::String(s),
+            Bson::Array(items) => {
+                let mut arr = Vector::new();
+                for item in items {
+                    arr.push_back(Value::from_bson(item)?);
+                }
+                Value::Array(arr)
+            }
+            Bson::Document(doc) => {
+                let mut obj = Object::new();
+                for (key, value) in doc {
+                    obj.insert(key, Value::from_bson(value)?);
+                }
+                Value::Object(obj)
+            }
+            other => other.into_relaxed_extjson().into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::bson::doc;
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_documents() {
+        let value: Value = json!({
+            "name": "ann",
+            "small": 5,
+            "negative": -5,
+            "large": 1u64 << 40,
+            "very_negative": i64::min_value(),
+            "ratio": 0.25,
+            "tags": ["a", null, true],
+            "nested": {"empty": {}}
+        })
+        .into();
+        let bson = value.to_bson().unwrap();
+        let doc = bson.as_document().unwrap();
+        assert_eq!(doc.get("small"), Some(&Bson::Int32(5)));
+        assert_eq!(doc.get("negative"), Some(&Bson::Int32(-5)));
+        assert_eq!(doc.get("large"), Some(&Bson::Int64(1 << 40)));
+        assert_eq!(doc.get("ratio"), Some(&Bson::Double(0.25)));
+        assert_eq!(Value::from_bson(bson).unwrap(), value);
+    }
+
+    #[test]
+    fn rejects_u64_overflow() {
+        let value = Value::Number(Number::PosInt(u64::max_value()));
+        assert_eq!(
+            value.to_bson(),
+            Err(BsonError::IntegerOutOfRange(u64::max_value()))
+        );
+        let value = Value::Number(Number::PosInt(i64::max_value() as u64));
+        assert_eq!(value.to_bson(), Ok(Bson::Int64(i64::max_value())));
+    }
+
+    #[test]
+    fn reads_other_types_as_extended_json() {
+        let id = ::bson::oid::ObjectId::new();
+        let value = Value::from_bson(Bson::Document(doc! {"_id": id})).unwrap();
+        let expected: Value = json!({"_id": {"$oid": id.to_hex()}}).into();
+        assert_eq!(value, expected);
+        assert!(Value::from_bson(Bson::Double(f64::NAN)).is_err());
+    }
+}
@@ -0,0 +1,307 @@
+//! The JSON Canonicalization Scheme of RFC 8785.
+
+use crate::{Number, Value};
+use std::fmt::{self, Write};
+use std::io;
+
+/// Writes a string literal, escaping only what JSON requires and leaving other characters as is.
+fn write_escaped<W: Write>(out: &mut W, s: &str) -> fmt::Result {
+    out.write_char('"')?;
+    for c in s.chars() {
+        match c {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\u{8}' => out.write_str("\\b")?,
+            '\u{c}' => out.write_str("\\f")?,
+            '\n' => out.write_str("\\n")?,
+            '\r' => out.write_str("\\r")?,
+            '\t' => out.write_str("\\t")?,
+            c if c < ' ' => write!(out, "\\u{:04x}", c as u32)?,
+            c => out.write_char(c)?,
+        }
+    }
+    out.write_char('"')
+}
+
+pub(crate) fn write_string(out: &mut String, s: &str) {
+    write_escaped(out, s).unwrap();
+}
+
+/// Writes a double the way ECMAScript's `Number.prototype.toString` does, which RFC 8785 requires.
+fn write_f64<W: Write>(out: &mut W, f: f64) -> fmt::Result {
+    if f == 0.0 {
+        return out.write_char('0');
+    }
+    if f < 0.0 {
+        out.write_char('-')?;
+    }
+
+    // Rust's exponent formatting gives the shortest digits that round-trip, which is what
+    // ECMAScript uses as well. The value is 0.digits * 10^n.
+    let scientific = format!("{:e}", f.abs());
+    let (mantissa, exponent) = scientific.split_at(scientific.find('e').unwrap());
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent[1..].parse::<i32>().unwrap() + 1;
+
+    if k <= n && n <= 21 {
+        out.write_str(&digits)?;
+        for _ in k..n {
+            out.write_char('0')?;
+        }
+    } else if 0 < n && n <= 21 {
+        out.write_str(&digits[..n as usize])?;
+        out.write_char('.')?;
+        out.write_str(&digits[n as usize..])?;
+    } else if -6 < n && n <= 0 {
+        out.write_str("0.")?;
+        for _ in n..0 {
+            out.write_char('0')?;
+        }
+        out.write_str(&digits)?;
+    } else {
+        out.write_str(&digits[..1])?;
+        if k > 1 {
+            out.write_char('.')?;
+            out.write_str(&digits[1..])?;
+        }
+        write!(out, "e{}{}", if n > 0 { '+' } else { '-' }, (n - 1).abs())?;
+    }
+    Ok(())
+}
+
+/// Numbers are treated as doubles, so integers beyond 2^53 are rounded as they would be in
+/// ECMAScript. A number beyond the range of a double has no canonical form and is written as is.
+fn write_number<W: Write>(out: &mut W, n: &Number) -> fmt::Result {
+    let f = n.to_f64_lossy();
+    if f.is_finite() {
+        write_f64(out, f)
+    } else {
+        write!(out, "{}", n)
+    }
+}
+
+/// Writes every number as its exact value, so that unequal numbers never share a form. Integral
+/// doubles are written out in full, since beyond 2^53 their shortest round-trip digits name a
+/// different integer.
+#[cfg(feature = "hash")]
+fn write_exact_number<W: Write>(out: &mut W, n: &Number) -> fmt::Result {
+    match n {
+        Number::PosInt(u) => write!(out, "{}", u),
+        Number::NegInt(i) => write!(out, "{}", i),
+        Number::Float(f) if *f != 0.0 && f.fract() == 0.0 => write!(out, "{:.0}", f),
+        Number::Float(f) => write_f64(out, *f),
+        #[cfg(feature = "arbitrary_precision")]
+        Number::Big(s) => out.write_str(s),
+    }
+}
+
+fn write_canonical<W: Write>(
+    out: &mut W,
+    value: &Value,
+    number: fn(&mut W, &Number) -> fmt::Result,
+) -> fmt::Result {
+    match value {
+        Value::Null => out.write_str("null"),
+        Value::Bool(b) => out.write_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => number(out, n),
+        Value::String(s) => write_escaped(out, s),
+        Value::Array(arr) => {
+            out.write_char('[')?;
+            for (idx, item) in arr.iter().enumerate() {
+                if idx > 0 {
+                    out.write_char(',')?;
+                }
+                write_canonical(out, item, number)?;
+            }
+            out.write_char(']')
+        }
+        Value::Object(obj) => {
+            // Keys are stored in UTF-8 order, but the scheme orders them by UTF-16 code units. The
+            // two only differ for characters outside the Basic Multilingual Plane.
+            let mut entries: Vec<_> = obj.iter().collect();
+            entries.sort_by(|a, b| a.0.encode_utf16().cmp(b.0.encode_utf16()));
+            out.write_char('{')?;
+            for (idx, (key, value)) in entries.into_iter().enumerate() {
+                if idx > 0 {
+                    out.write_char(',')?;
+                }
+                write_escaped(out, key)?;
+                out.write_char(':')?;
+                write_canonical(out, value, number)?;
+            }
+            out.write_char('}')
+        }
+    }
+}
+
+/// Forwards formatted output to an `io::Write`, keeping the error that `fmt::Error` cannot carry.
+struct IoAdapter<W> {
+    inner: W,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Write for IoAdapter<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.inner.write_all(s.as_bytes()).map_err(|e| {
+            self.error = Some(e);
+            fmt::Error
+        })
+    }
+}
+
+fn write_io<W: io::Write>(
+    value: &Value,
+    writer: W,
+    number: fn(&mut IoAdapter<W>, &Number) -> fmt::Result,
+) -> io::Result<()> {
+    let mut adapter = IoAdapter {
+        inner: writer,
+        error: None,
+    };
+    write_canonical(&mut adapter, value, number).map_err(|_| {
+        adapter
+            .error
+            .take()
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::Other, "formatting failed"))
+    })
+}
+
+impl Value {
+    /// Serializes the value following RFC 8785: no whitespace, keys sorted by their UTF-16 code
+    /// units, minimal string escaping and numbers formatted as ECMAScript doubles.
+    pub fn to_canonical_string(&self) -> String {
+        let mut out = String::new();
+        write_canonical(&mut out, self, write_number).unwrap();
+        out
+    }
+
+    /// Streams the same bytes as `to_canonical_string` into a writer without building the whole
+    /// string first.
+    pub fn write_canonical<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_io(self, writer, write_number)
+    }
+
+    /// Streams the canonical form with every number written exactly, for hashing. Unlike the RFC
+    /// 8785 form, it tells apart integers that round to the same double.
+    #[cfg(feature = "hash")]
+    pub(crate) fn write_exact<W: io::Write>(&self, writer: W) -> io::Result<()> {
+        write_io(self, writer, write_exact_number)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(bits: u64) -> String {
+        Value::Number(Number::from_f64(f64::from_bits(bits)).unwrap()).to_canonical_string()
+    }
+
+    #[test]
+    fn formats_rfc_number_vectors() {
+        let vectors = [
+            (0x0000000000000000, "0"),
+            (0x8000000000000000, "0"),
+            (0x0000000000000001, "5e-324"),
+            (0x8000000000000001, "-5e-324"),
+            (0x7fefffffffffffff, "1.7976931348623157e+308"),
+            (0xffefffffffffffff, "-1.7976931348623157e+308"),
+            (0x4340000000000000, "9007199254740992"),
+            (0xc340000000000000, "-9007199254740992"),
+            (0x4430000000000000, "295147905179352830000"),
+            (0x44b52d02c7e14af5, "9.999999999999997e+22"),
+            (0x44b52d02c7e14af6, "1e+23"),
+            (0x44b52d02c7e14af7, "1.0000000000000001e+23"),
+            (0x444b1ae4d6e2ef4e, "999999999999999700000"),
+            (0x444b1ae4d6e2ef4f, "999999999999999900000"),
+            (0x444b1ae4d6e2ef50, "1e+21"),
+            (0x3eb0c6f7a0b5ed8c, "0.000001"),
+            (0x3eb0c6f7a0b5ed8d, "0.0000010000000000000002"),
+            (0x41b3de4355555553, "333333333.3333332"),
+            (0x41b3de4355555554, "333333333.33333325"),
+            (0x41b3de4355555555, "333333333.3333333"),
+            (0x41b3de4355555556, "333333333.3333334"),
+            (0x41b3de4355555557, "333333333.33333343"),
+            (0xbecbf647612f3696, "-0.0000033333333333333333"),
+            (0x43143ff3c1cb0959, "1424953923781206.2"),
+        ];
+        for (bits, expected) in vectors.iter() {
+            assert_eq!(format(*bits), *expected, "bits {:x}", bits);
+        }
+    }
+
+    #[test]
+    fn formats_integers() {
+        assert_eq!(
+            Value::Number(Number::PosInt(42)).to_canonical_string(),
+            "42"
+        );
+        assert_eq!(
+            Value::Number(Number::NegInt(-7)).to_canonical_string(),
+            "-7"
+        );
+        assert_eq!(
+            Value::Number(Number::PosInt(u64::max_value())).to_canonical_string(),
+            "18446744073709552000"
+        );
+    }
+
+    #[test]
+    fn canonicalizes_rfc_example() {
+        let input = r#"{
+            "numbers": [333333333.33333329, 1E30, 4.50, 2e-3, 0.000000000000000000000000001],
+            "string": "\u20ac$\u000F\u000aA'\u0042\u0022\u005c\\\"\/",
+            "literals": [null, true, false]
+        }"#;
+        let value = Value::from_str_borrowed(input).unwrap();
+        assert_eq!(
+            value.to_canonical_string(),
+            r#"{"literals":[null,true,false],"numbers":[333333333.3333333,1e+30,4.5,0.002,1e-27],"string":"€$\u000f\nA'B\"\\\\\"/"}"#
+        );
+    }
+
+    #[test]
+    fn sorts_keys_by_utf16() {
+        let input = r#"{
+            "\u20ac": "Euro Sign",
+            "\r": "Carriage Return",
+            "\ufb33": "Hebrew Letter Dalet With Dagesh",
+            "1": "One",
+            "\ud83d\ude00": "Emoji: Grinning Face",
+            "\u0080": "Control",
+            "\u00f6": "Latin Small Letter O With Diaeresis"
+        }"#;
+        let canonical = Value::from_str_borrowed(input)
+            .unwrap()
+            .to_canonical_string();
+        let order = [
+            "Carriage Return",
+            "One",
+            "Control",
+            "Latin Small Letter O With Diaeresis",
+            "Euro Sign",
+            "Emoji: Grinning Face",
+            "Hebrew Letter Dalet With Dagesh",
+        ];
+        let positions: Vec<usize> = order.iter().map(|v| canonical.find(v).unwrap()).collect();
+        let mut sorted = positions.clone();
+        sorted.sort();
+        assert_eq!(positions, sorted);
+    }
+
+    #[test]
+    fn streams_to_writer() {
+        let value = Value::from_str_borrowed(r#"{"b": [1.5, "x\n"], "a": null}"#).unwrap();
+        let mut bytes = Vec::new();
+        value.write_canonical(&mut bytes).unwrap();
+        assert_eq!(
+            String::from_utf8(bytes).unwrap(),
+            value.to_canonical_string()
+        );
+
+        let mut short = [0u8; 8];
+        let error = value.write_canonical(&mut short[..]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::WriteZero);
+    }
+}
@@ -0,0 +1,132 @@
+//! A position in a document that can be moved around and edited through.
+
+use crate::pointer::{escape, step, step_mut};
+use crate::Value;
+use std::mem;
+
+/// A position in a document, kept as the path from the root. Every operation resolves the path
+/// afresh, so the cursor holds no references into the tree and can edit freely. The cursor
+/// borrows the root exclusively, and since it only moves to nodes that exist and only replaces
+/// the node it is on, its path always resolves.
+pub struct Cursor<'a> {
+    root: &'a mut Value,
+    path: Vec<String>,
+}
+
+impl<'a> Cursor<'a> {
+    /// Moves to the child of the current node under `token`: a key of an object, or an index in
+    /// decimal of an array. Returns false, staying put, if there is no such child.
+    pub fn down(&mut self, token: &str) -> bool {
+        if step(self.get(), token).is_none() {
+            return false;
+        }
+        self.path.push(token.to_owned());
+        true
+    }
+
+    /// Moves to the element at `idx` of the current array, as `down` does.
+    pub fn down_index(&mut self, idx: usize) -> bool {
+        self.down(&idx.to_string())
+    }
+
+    /// Moves to the parent of the current node. Returns false at the root.
+    pub fn up(&mut self) -> bool {
+        self.path.pop().is_some()
+    }
+
+    /// The number of steps from the root to the current node.
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// The JSON Pointer to the current node.
+    pub fn pointer(&self) -> String {
+        self.path
+            .iter()
+            .map(|token| format!("/{}", escape(token)))
+            .collect()
+    }
+
+    pub fn get(&self) -> &Value {
+        self.path
+            .iter()
+            .try_fold(&*self.root, |value, token| step(value, token))
+            .unwrap()
+    }
+
+    /// Only the containers along the path are copied, so earlier clones of the document are left
+    /// untouched.
+    pub fn get_mut(&mut self) -> &mut Value {
+        self.path
+            .iter()
+            .try_fold(&mut *self.root, |value, token| step_mut(value, token))
+            .unwrap()
+    }
+
+    /// Replaces the current node, returning the value it held. The cursor stays on the new value.
+    pub fn set(&mut self, value: Value) -> Value {
+        mem::replace(self.get_mut(), value)
+    }
+}
+
+impl Value {
+    /// A cursor positioned at `self`.
+    pub fn cursor(&mut self) -> Cursor<'_> {
+        Cursor {
+            root: self,
+            path: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn navigates_and_edits() {
+        let mut value: Value = json!({"users": [{"name": "a"}, {"name": "b", "tags": []}]}).into();
+        let snapshot = value.clone();
+        let mut cursor = value.cursor();
+        assert!(cursor.down("users"));
+        assert!(cursor.down_index(1));
+        assert!(cursor.down("name"));
+        assert_eq!(cursor.pointer(), "/users/1/name");
+        assert_eq!(cursor.get(), &Value::from("b"));
+        assert_eq!(cursor.set(Value::from("c")), Value::from("b"));
+
+        assert!(!cursor.down("missing"));
+        assert!(cursor.up());
+        assert!(!cursor.down("missing"));
+        assert!(!cursor.down_index(0));
+        assert!(cursor.up());
+        assert!(!cursor.down_index(2));
+        assert!(!cursor.down("01"));
+        assert_eq!(cursor.get().as_array().map(|arr| arr.len()), Some(2));
+        assert!(cursor.up());
+        assert_eq!(cursor.depth(), 0);
+        assert!(!cursor.up());
+        assert_eq!(cursor.pointer(), "");
+
+        assert_eq!(
+            value,
+            json!({"users": [{"name": "a"}, {"name": "c", "tags": []}]})
+        );
+        assert_eq!(
+            snapshot,
+            json!({"users": [{"name": "a"}, {"name": "b", "tags": []}]})
+        );
+    }
+
+    #[test]
+    fn escapes_pointer() {
+        let mut value: Value = json!({"a/b": {"~": 1}}).into();
+        let mut cursor = value.cursor();
+        assert!(cursor.down("a/b"));
+        assert!(cursor.down("~"));
+        assert_eq!(cursor.pointer(), "/a~1b/~0");
+        cursor.get_mut().map_scalars(|n| *n = Value::Null);
+        assert_eq!(value, json!({"a/b": {"~": null}}));
+    }
+}
@@ -0,0 +1,156 @@
+use crate::pointer::escape;
+use crate::Value;
+use std::cmp::Ordering;
+
+/// The JSON Pointers that differ between two documents, see `Value::diff_summary`. Each list is in
+/// document order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiffSummary {
+    /// Paths present only in the newer document.
+    pub added: Vec<String>,
+    /// Paths present only in the older document.
+    pub removed: Vec<String>,
+    /// Paths present in both whose values differ, other than by entries added or removed below.
+    pub changed: Vec<String>,
+}
+
+impl DiffSummary {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Value {
+    /// Lists the paths added, removed and changed going from `self` to `other`. Objects are
+    /// compared key by key and arrays index by index, so an element inserted into the middle of an
+    /// array changes every later index. A value of a different type, or a scalar that differs, is
+    /// reported as changed without looking inside. Subtrees that share their storage, as after a
+    /// cheap clone, are skipped without being compared.
+    pub fn diff_summary(&self, other: &Value) -> DiffSummary {
+        let mut summary = DiffSummary::default();
+        let mut stack = vec![(String::new(), Some(self), Some(other))];
+        while let Some((path, old, new)) = stack.pop() {
+            let (old, new) = match (old, new) {
+                (Some(old), Some(new)) => (old, new),
+                (Some(_), None) => {
+                    summary.removed.push(path);
+                    continue;
+                }
+                (None, Some(_)) => {
+                    summary.added.push(path);
+                    continue;
+                }
+                (None, None) => continue,
+            };
+            if old.ptr_eq(new) {
+                continue;
+            }
+            match (old, new) {
+                (Value::Array(a), Value::Array(b)) => {
+                    for idx in (0..a.len().max(b.len())).rev() {
+                        stack.push((format!("{}/{}", path, idx), a.get(idx), b.get(idx)));
+                    }
+                }
+                (Value::Object(a), Value::Object(b)) => {
+                    let mut children = Vec::new();
+                    let mut left = a.iter().peekable();
+                    let mut right = b.iter().peekable();
+                    loop {
+                        let order = match (left.peek(), right.peek()) {
+                            (Some((l, _)), Some((r, _))) => l.cmp(r),
+                            (Some(_), None) => Ordering::Less,
+                            (None, Some(_)) => Ordering::Greater,
+                            (None, None) => break,
+                        };
+                        let (key, a_value, b_value) = match order {
+                            Ordering::Less => {
+                                let (key, value) = left.next().unwrap();
+                                (key, Some(value), None)
+                            }
+                            Ordering::Greater => {
+                                let (key, value) = right.next().unwrap();
+                                (key, None, Some(value))
+                            }
+                            Ordering::Equal => {
+                                let (key, a_value) = left.next().unwrap();
+                                let (_, b_value) = right.next().unwrap();
+                                (key, Some(a_value), Some(b_value))
+                            }
+                        };
+                        children.push((format!("{}/{}", path, escape(key)), a_value, b_value));
+                    }
+                    stack.extend(children.into_iter().rev());
+                }
+                _ if old == new => {}
+                _ => summary.changed.push(path),
+            }
+        }
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn summarizes_changes() {
+        let old: Value = json!({
+            "name": "before",
+            "tags": ["a", "b"],
+            "old": 1,
+            "shared": {"k": [1, 2]},
+            "shape": {"x": 1}
+        })
+        .into();
+        let mut new = old.clone();
+        new["name"] = Value::from("after");
+        new.as_object_mut().unwrap().remove("old");
+        new["tags"]
+            .as_array_mut()
+            .unwrap()
+            .push_back(Value::from("c"));
+        new.as_object_mut()
+            .unwrap()
+            .insert("a/b".to_owned(), Value::Bool(true));
+        new["shape"] = json!([1]).into();
+
+        let summary = old.diff_summary(&new);
+        assert_eq!(summary.added, vec!["/a~1b", "/tags/2"]);
+        assert_eq!(summary.removed, vec!["/old"]);
+        assert_eq!(summary.changed, vec!["/name", "/shape"]);
+
+        let reverse = new.diff_summary(&old);
+        assert_eq!(reverse.added, summary.removed);
+        assert_eq!(reverse.removed, summary.added);
+        assert_eq!(reverse.changed, summary.changed);
+    }
+
+    #[test]
+    fn finds_edit_in_large_clone() {
+        let old = Value::from_array_iter((0..1000u64).map(Value::from));
+        let mut new = old.clone();
+        new[500] = Value::Null;
+        let summary = old.diff_summary(&new);
+        assert_eq!(summary.changed, vec!["/500"]);
+        assert!(summary.added.is_empty() && summary.removed.is_empty());
+
+        let old: Value = json!({"items": (0..1000).collect::<Vec<_>>(), "n": 1}).into();
+        let mut new = old.clone();
+        new["items"][500] = Value::from("x");
+        assert_eq!(old.diff_summary(&new).changed, vec!["/items/500"]);
+    }
+
+    #[test]
+    fn equal_documents_have_empty_summary() {
+        let a: Value = json!({"x": [1, {"y": null}], "z": 1.5}).into();
+        let b: Value = json!({"x": [1, {"y": null}], "z": 1.5}).into();
+        assert!(a.diff_summary(&b).is_empty());
+        assert!(a.diff_summary(&a.clone()).is_empty());
+        assert_eq!(
+            Value::Null.diff_summary(&Value::Bool(false)).changed,
+            vec![String::new()]
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Compact renderings of values meant for logs and diagnostics rather than round-tripping.
+
+use crate::canonical::write_string;
+use crate::Value;
+
+const ELLIPSIS: &str = "…";
+
+fn marker(count: usize) -> String {
+    format!("{}({} more)", ELLIPSIS, count)
+}
+
+/// Writes a bare ellipsis in place of a value that does not fit at all.
+fn elide(out: &mut String, budget: usize) -> bool {
+    if budget >= ELLIPSIS.len() {
+        out.push_str(ELLIPSIS);
+    }
+    false
+}
+
+fn truncate_string(out: &mut String, s: &str, budget: usize) -> bool {
+    let mut escaped = String::new();
+    write_string(&mut escaped, s);
+    if escaped.len() <= budget {
+        out.push_str(&escaped);
+        return true;
+    }
+
+    let mut remaining = s.chars().count();
+    if 2 + marker(remaining).len() > budget {
+        return elide(out, budget);
+    }
+    let mut prefix = String::new();
+    let mut buffer = String::new();
+    for c in s.chars() {
+        buffer.clear();
+        write_string(&mut buffer, c.encode_utf8(&mut [0; 4]));
+        let escaped_char = &buffer[1..buffer.len() - 1];
+        if 2 + prefix.len() + escaped_char.len() + marker(remaining - 1).len() > budget {
+            break;
+        }
+        prefix.push_str(escaped_char);
+        remaining -= 1;
+    }
+    out.push('"');
+    out.push_str(&prefix);
+    out.push_str(&marker(remaining));
+    out.push('"');
+    false
+}
+
+/// Writes the entries produced by `write_entry` between `open` and `close`, eliding the tail once
+/// `budget` bytes would be exceeded. Returns whether everything was written.
+fn truncate_container<T, F>(
+    out: &mut String,
+    entries: &[T],
+    open: char,
+    close: char,
+    budget: usize,
+    mut write_entry: F,
+) -> bool
+where
+    F: FnMut(&mut String, &T, usize) -> bool,
+{
+    if !entries.is_empty() && 2 + marker(entries.len()).len() > budget {
+        return elide(out, budget);
+    }
+
+    let start = out.len();
+    out.push(open);
+    for (idx, entry) in entries.iter().enumerate() {
+        let rest = entries.len() - idx;
+        let separator = if idx > 0 { 1 } else { 0 };
+        // Leave room to elide whatever follows this entry should it be cut short.
+        let reserve = if rest > 1 {
+            1 + marker(rest - 1).len()
+        } else {
+            0
+        } + 1;
+        let used = out.len() - start;
+        let available = budget.saturating_sub(used + separator + reserve);
+
+        let mut rendered = String::new();
+        let complete = write_entry(&mut rendered, entry, available);
+        if rendered.is_empty() {
+            if separator > 0 {
+                out.push(',');
+            }
+            out.push_str(&marker(rest));
+            out.push(close);
+            return false;
+        }
+        if separator > 0 {
+            out.push(',');
+        }
+        out.push_str(&rendered);
+        if !complete {
+            if rest > 1 {
+                out.push(',');
+                out.push_str(&marker(rest - 1));
+            }
+            out.push(close);
+            return false;
+        }
+    }
+    out.push(close);
+    true
+}
+
+fn truncate_value(out: &mut String, value: &Value, budget: usize) -> bool {
+    let scalar = match value {
+        Value::Null => "null".to_owned(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => return truncate_string(out, s, budget),
+        Value::Array(arr) => {
+            let items: Vec<&Value> = arr.iter().collect();
+            return truncate_container(out, &items, '[', ']', budget, |out, item, budget| {
+                truncate_value(out, item, budget)
+            });
+        }
+        Value::Object(obj) => {
+            let entries: Vec<(&String, &Value)> = obj.iter().collect();
+            return truncate_container(out, &entries, '{', '}', budget, |out, entry, budget| {
+                let mut key = String::new();
+                write_string(&mut key, entry.0);
+                if key.len() + 1 + ELLIPSIS.len() > budget {
+                    return false;
+                }
+                out.push_str(&key);
+                out.push(':');
+                truncate_value(out, entry.1, budget - key.len() - 1)
+            });
+        }
+    };
+    if scalar.len() <= budget {
+        out.push_str(&scalar);
+        true
+    } else {
+        elide(out, budget)
+    }
+}
+
+fn write_to_depth(out: &mut String, value: &Value, depth: usize) {
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::String(s) => write_string(out, s),
+        Value::Array(arr) if arr.len() == 0 => out.push_str("[]"),
+        Value::Object(obj) if obj.is_empty() => out.push_str("{}"),
+        Value::Array(_) | Value::Object(_) if depth == 0 => out.push_str("\"...\""),
+        Value::Array(arr) => {
+            out.push('[');
+            for (idx, item) in arr.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_to_depth(out, item, depth - 1);
+            }
+            out.push(']');
+        }
+        Value::Object(obj) => {
+            out.push('{');
+            for (idx, (key, value)) in obj.iter().enumerate() {
+                if idx > 0 {
+                    out.push(',');
+                }
+                write_string(out, key);
+                out.push(':');
+                write_to_depth(out, value, depth - 1);
+            }
+            out.push('}');
+        }
+    }
+}
+
+impl Value {
+    /// Renders compact JSON of at most `max_len` bytes. Once the output would grow too long, the
+    /// rest of a string, array or object is replaced with a `…(N more)` marker counting the
+    /// characters, elements or entries left out, and a value that does not fit at all becomes `…`.
+    /// This is meant for logging and is not valid JSON once anything has been elided.
+    pub fn display_truncated(&self, max_len: usize) -> String {
+        let mut out = String::new();
+        truncate_value(&mut out, self, max_len);
+        out
+    }
+
+    /// Renders compact JSON showing `depth` levels of nesting. A non-empty array or object nested
+    /// deeper than that is replaced with the string `"..."`, so the output stays valid JSON. At
+    /// depth zero only a scalar or empty root is shown.
+    pub fn to_string_max_depth(&self, depth: usize) -> String {
+        let mut out = String::new();
+        write_to_depth(&mut out, self, depth);
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn keeps_small_values_whole() {
+        let value: Value = json!({"a": [1, "two", null], "b": {"c": true}}).into();
+        assert_eq!(
+            value.display_truncated(100),
+            r#"{"a":[1,"two",null],"b":{"c":true}}"#
+        );
+    }
+
+    #[test]
+    fn elides_large_arrays() {
+        let value = Value::from_array_iter((0..1000u64).map(|n| Value::Number(n.into())));
+        for max_len in &[20, 50, 200] {
+            let output = value.display_truncated(*max_len);
+            assert!(output.len() <= *max_len, "{} is too long", output);
+            assert!(output.starts_with("[0,1,"));
+            assert!(output.ends_with(" more)]"));
+        }
+        assert_eq!(value.display_truncated(20), "[0,1,…(998 more)]");
+    }
+
+    #[test]
+    fn elides_long_strings() {
+        let value = Value::String("abcdefghijklmnopqrstuvwxyz".to_owned());
+        assert_eq!(value.display_truncated(20), "\"abcdef…(20 more)\"");
+        assert_eq!(value.display_truncated(5), "…");
+        assert_eq!(value.display_truncated(2), "");
+    }
+
+    #[test]
+    fn elides_nested_values() {
+        let value: Value = json!({
+            "items": [{"name": "first", "tags": ["x", "y", "z"]}, {"name": "second"}],
+            "total": 2
+        })
+        .into();
+        for max_len in 0..60 {
+            let output = value.display_truncated(max_len);
+            assert!(output.len() <= max_len, "{} is too long", output);
+        }
+        let output = value.display_truncated(40);
+        assert_eq!(output, "{\"items\":[…,…(1 more)],…(1 more)}");
+        let output = value.display_truncated(70);
+        assert!(output.len() <= 70);
+        assert!(output.starts_with("{\"items\":[{\"name\":\"first\""));
+        assert!(output.contains("more)"));
+    }
+
+    #[test]
+    fn limits_depth() {
+        let value: Value = json!({"a": 1, "b": {"c": [2, {"d": "e"}], "f": []}}).into();
+        assert_eq!(value.to_string_max_depth(0), r#""...""#);
+        assert_eq!(value.to_string_max_depth(1), r#"{"a":1,"b":"..."}"#);
+        assert_eq!(
+            value.to_string_max_depth(2),
+            r#"{"a":1,"b":{"c":"...","f":[]}}"#
+        );
+        assert_eq!(
+            value.to_string_max_depth(4),
+            r#"{"a":1,"b":{"c":[2,{"d":"e"}],"f":[]}}"#
+        );
+        assert_eq!(Value::Bool(true).to_string_max_depth(0), "true");
+    }
+}
@@ -0,0 +1,171 @@
+//! Typed extraction of object fields without going through serde.
+
+use crate::Value;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+
+/// Types that `Value::get_as` can extract.
+pub trait FromValue: Sized {
+    /// How the expected type is named in an `ExtractError`.
+    const EXPECTED: &'static str;
+
+    /// Converts `value`, or returns `None` if it does not hold this type.
+    fn from_value(value: &Value) -> Option<Self>;
+}
+
+impl FromValue for bool {
+    const EXPECTED: &'static str = "boolean";
+
+    fn from_value(value: &Value) -> Option<bool> {
+        value.as_bool()
+    }
+}
+
+impl FromValue for i64 {
+    const EXPECTED: &'static str = "i64";
+
+    fn from_value(value: &Value) -> Option<i64> {
+        value.as_number()?.as_i64()
+    }
+}
+
+impl FromValue for u64 {
+    const EXPECTED: &'static str = "u64";
+
+    fn from_value(value: &Value) -> Option<u64> {
+        value.as_number()?.as_u64()
+    }
+}
+
+impl FromValue for i32 {
+    const EXPECTED: &'static str = "i32";
+
+    fn from_value(value: &Value) -> Option<i32> {
+        i32::try_from(i64::from_value(value)?).ok()
+    }
+}
+
+impl FromValue for u32 {
+    const EXPECTED: &'static str = "u32";
+
+    fn from_value(value: &Value) -> Option<u32> {
+        u32::try_from(u64::from_value(value)?).ok()
+    }
+}
+
+/// Integers are converted, so this accepts any number.
+impl FromValue for f64 {
+    const EXPECTED: &'static str = "f64";
+
+    fn from_value(value: &Value) -> Option<f64> {
+        value.as_number()?.as_f64()
+    }
+}
+
+impl FromValue for String {
+    const EXPECTED: &'static str = "string";
+
+    fn from_value(value: &Value) -> Option<String> {
+        value.as_str().map(str::to_owned)
+    }
+}
+
+/// An array whose elements all convert to `T`.
+impl<T: FromValue> FromValue for Vec<T> {
+    const EXPECTED: &'static str = "array";
+
+    fn from_value(value: &Value) -> Option<Vec<T>> {
+        value.as_array()?.iter().map(T::from_value).collect()
+    }
+}
+
+/// Returned by `Value::get_as` when a field is missing or holds the wrong type.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ExtractError {
+    pub key: String,
+    /// The type that was asked for, see `FromValue::EXPECTED`.
+    pub expected: &'static str,
+    /// The type of the value found, or `None` if the key is missing.
+    pub found: Option<&'static str>,
+}
+
+impl fmt::Display for ExtractError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.found {
+            Some(found) => write!(
+                formatter,
+                "field \"{}\": expected {}, found {}",
+                self.key, self.expected, found
+            ),
+            None => write!(
+                formatter,
+                "field \"{}\": expected {}, found nothing",
+                self.key, self.expected
+            ),
+        }
+    }
+}
+
+impl Error for ExtractError {}
+
+impl Value {
+    /// Converts the field `key` of an object to `T`. A value that is not an object has no fields.
+    /// For a `Vec`, the error reports the field as a whole even if only one element is wrong.
+    pub fn get_as<T: FromValue>(&self, key: &str) -> Result<T, ExtractError> {
+        let field = self.as_object().and_then(|obj| obj.get(key));
+        field.and_then(T::from_value).ok_or_else(|| ExtractError {
+            key: key.to_owned(),
+            expected: T::EXPECTED,
+            found: field.map(Value::type_name),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_fields() {
+        let value: Value =
+            json!({"id": 7, "name": "ada", "scores": [1, 2, 3], "ratio": 2, "ok": true}).into();
+        assert_eq!(value.get_as::<i64>("id"), Ok(7));
+        assert_eq!(value.get_as::<u32>("id"), Ok(7));
+        assert_eq!(value.get_as::<String>("name"), Ok("ada".to_owned()));
+        assert_eq!(value.get_as::<Vec<i64>>("scores"), Ok(vec![1, 2, 3]));
+        assert_eq!(value.get_as::<f64>("ratio"), Ok(2.0));
+        assert_eq!(value.get_as::<bool>("ok"), Ok(true));
+    }
+
+    #[test]
+    fn reports_mismatches() {
+        let value: Value = json!({"id": "7", "scores": [1, "two"], "big": -1}).into();
+        let err = value.get_as::<i64>("id").unwrap_err();
+        assert_eq!(
+            err,
+            ExtractError {
+                key: "id".to_owned(),
+                expected: "i64",
+                found: Some("string"),
+            }
+        );
+        assert_eq!(err.to_string(), "field \"id\": expected i64, found string");
+
+        let err = value.get_as::<Vec<i64>>("scores").unwrap_err();
+        assert_eq!((err.expected, err.found), ("array", Some("array")));
+        assert_eq!(
+            value.get_as::<u64>("big").unwrap_err().found,
+            Some("number")
+        );
+
+        let err = value.get_as::<String>("missing").unwrap_err();
+        assert_eq!(err.found, None);
+        assert_eq!(
+            err.to_string(),
+            "field \"missing\": expected string, found nothing"
+        );
+        assert!(Value::Null.get_as::<bool>("id").is_err());
+    }
+}
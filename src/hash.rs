@@ -0,0 +1,78 @@
+use crate::Value;
+use sha2::{Digest, Sha256};
+
+impl Value {
+    /// SHA-256 of the RFC 8785 serialization, so equal documents hash identically no matter how
+    /// they were built. Numbers are the one exception: RFC 8785 rounds them to doubles, which
+    /// would give integers beyond 2^53 such as distinct IDs the same digest, so they are hashed
+    /// by their exact value instead.
+    pub fn content_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        self.write_exact(&mut hasher)
+            .expect("writing to a hasher cannot fail");
+        hasher.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Number, Object};
+    use serde_json::json;
+
+    #[test]
+    fn equal_documents_hash_identically() {
+        let parsed = Value::from_str_borrowed(r#"{"b": [1.0, "x"], "a": {"c": null}}"#).unwrap();
+
+        let mut inner = Object::new();
+        inner.insert("c".to_owned(), Value::Null);
+        let mut built = Object::new();
+        built.insert("a".to_owned(), Value::Object(inner));
+        built.insert("b".to_owned(), json!([1, "x"]).into());
+        let built = Value::Object(built);
+
+        assert_eq!(parsed.content_hash(), built.content_hash());
+
+        let changed: Value = json!({"a": {"c": null}, "b": [2, "x"]}).into();
+        assert_ne!(parsed.content_hash(), changed.content_hash());
+    }
+
+    #[test]
+    fn distinguishes_integers_beyond_double_precision() {
+        let a: Value = json!({"id": 9_007_199_254_740_993u64}).into();
+        let b: Value = json!({"id": 9_007_199_254_740_992u64}).into();
+        assert_eq!(a.to_canonical_string(), b.to_canonical_string());
+        assert_ne!(a.content_hash(), b.content_hash());
+
+        let a: Value = json!([u64::max_value(), -9_223_372_036_854_775_807i64]).into();
+        let b: Value = json!([u64::max_value() - 1, -9_223_372_036_854_775_806i64]).into();
+        assert_ne!(a.content_hash(), b.content_hash());
+
+        // A double is hashed by its exact value, not its shortest digits, which for 2^60 are
+        // those of a different integer.
+        let float = Value::Number(Number::Float(1_152_921_504_606_846_976.0));
+        let int = Value::Number(Number::PosInt(1_152_921_504_606_847_000));
+        assert_ne!(float.content_hash(), int.content_hash());
+    }
+
+    #[test]
+    fn hashes_empty_object() {
+        let digest = Value::Object(Object::new()).content_hash();
+        let hex: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        assert_eq!(
+            hex,
+            "44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+    }
+
+    #[test]
+    fn hashes_through_writer() {
+        let value: Value = json!({"b": [1, 2.5, "three"], "a": {"c": null}}).into();
+        let mut hasher = Sha256::new();
+        value.write_canonical(&mut hasher).unwrap();
+        let digest: [u8; 32] = hasher.finalize().into();
+        let expected: [u8; 32] = Sha256::digest(value.to_canonical_string().as_bytes()).into();
+        assert_eq!(digest, expected);
+        assert_eq!(digest, value.content_hash());
+    }
+}
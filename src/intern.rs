@@ -0,0 +1,185 @@
+//! Sharing of key storage between objects that have the same keys.
+//!
+//! An `Object` keeps its sorted keys in their own persistent vector, apart from its values. When
+//! many objects have exactly the same keys, as records in NDJSON usually do, they can all hold
+//! clones of one key vector, so each distinct set of keys is stored once rather than once per
+//! object. Key vectors are copied on write like any other, so modifying the keys of one object
+//! leaves the others untouched.
+//!
+//! Keys are not interned one by one. An `Object` holds its keys as `String`s, which cannot share
+//! their text, so the unit of sharing is the whole key vector. Objects whose keys differ by a
+//! single key share nothing with each other, though each distinct set of keys is still stored
+//! only once however many objects have it.
+
+use crate::{Object, ParseError, Value};
+use librrb::Vector;
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+
+/// The key vectors handed out so far, by the keys they hold. Reusing a pool across documents, such
+/// as the lines of an NDJSON stream, shares keys between all of them.
+#[derive(Clone, Debug, Default)]
+pub struct KeyPool {
+    shapes: HashMap<Vec<String>, Vector<String>>,
+}
+
+impl KeyPool {
+    pub fn new() -> Self {
+        KeyPool::default()
+    }
+
+    /// The number of distinct sets of keys seen.
+    pub fn len(&self) -> usize {
+        self.shapes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes.is_empty()
+    }
+
+    /// Parses a JSON document, drawing the keys of every object from the pool.
+    pub fn parse(&mut self, s: &str) -> Result<Value, ParseError> {
+        let value = serde_json::from_str::<JsonValue>(s)?;
+        Ok(self.convert(value))
+    }
+
+    fn convert(&mut self, value: JsonValue) -> Value {
+        match value {
+            JsonValue::Array(arr) => {
+                let mut items = Vector::new();
+                for item in arr {
+                    items.push_back(self.convert(item));
+                }
+                Value::Array(items)
+            }
+            JsonValue::Object(map) => {
+                let mut entries: Vec<(String, JsonValue)> = map.into_iter().collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                let mut keys = Vec::with_capacity(entries.len());
+                let mut obj = Object::new();
+                for (key, value) in entries {
+                    keys.push(key);
+                    obj.values.push_back(self.convert(value));
+                }
+                obj.keys = self.keys_for(keys);
+                Value::Object(obj)
+            }
+            scalar => scalar.into(),
+        }
+    }
+
+    fn keys_for(&mut self, keys: Vec<String>) -> Vector<String> {
+        if let Some(shared) = self.shapes.get(&keys) {
+            return shared.clone();
+        }
+        let mut shared = Vector::new();
+        for key in keys.iter() {
+            shared.push_back(key.clone());
+        }
+        self.shapes.insert(keys, shared.clone());
+        shared
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    /// Approximates the memory held by object keys, counting each key allocation once however many
+    /// objects refer to it.
+    fn key_bytes(values: &[Value]) -> usize {
+        let mut seen = HashSet::new();
+        let mut total = 0;
+        for value in values {
+            value.for_each_node(|_, node| {
+                if let Value::Object(obj) = node {
+                    for key in obj.keys() {
+                        if seen.insert(key.as_ptr()) {
+                            total += key.capacity();
+                        }
+                    }
+                }
+            });
+        }
+        total
+    }
+
+    #[test]
+    fn shares_keys_between_records() {
+        let lines: Vec<String> = (0..1000)
+            .map(|i| {
+                format!(
+                    r#"{{"id": {}, "name": "n{}", "address": {{"city": "c"}}}}"#,
+                    i, i
+                )
+            })
+            .collect();
+        let mut pool = KeyPool::new();
+        let interned: Vec<Value> = lines.iter().map(|line| pool.parse(line).unwrap()).collect();
+        let plain: Vec<Value> = lines
+            .iter()
+            .map(|line| Value::from_str_borrowed(line).unwrap())
+            .collect();
+
+        assert_eq!(interned, plain);
+        assert_eq!(pool.len(), 2);
+        let (interned_bytes, plain_bytes) = (key_bytes(&interned), key_bytes(&plain));
+        assert!(
+            interned_bytes * 100 < plain_bytes,
+            "{} vs {}",
+            interned_bytes,
+            plain_bytes
+        );
+    }
+
+    #[test]
+    fn shares_keys_between_records_with_optional_fields() {
+        let lines: Vec<String> = (0..1000)
+            .map(|i| match i % 4 {
+                0 => format!(r#"{{"id": {}}}"#, i),
+                1 => format!(r#"{{"id": {}, "email": "e"}}"#, i),
+                2 => format!(r#"{{"id": {}, "phone": "p", "tags": {{"a": 1}}}}"#, i),
+                _ => format!(r#"{{"id": {}, "email": "e", "phone": "p"}}"#, i),
+            })
+            .collect();
+        let mut pool = KeyPool::new();
+        let interned: Vec<Value> = lines.iter().map(|line| pool.parse(line).unwrap()).collect();
+        let plain: Vec<Value> = lines
+            .iter()
+            .map(|line| Value::from_str_borrowed(line).unwrap())
+            .collect();
+        assert_eq!(interned, plain);
+
+        // Four sets of top-level keys and the nested `tags` object, each stored once.
+        assert_eq!(pool.len(), 5);
+        let (interned_bytes, plain_bytes) = (key_bytes(&interned), key_bytes(&plain));
+        assert!(
+            interned_bytes * 100 < plain_bytes,
+            "{} vs {}",
+            interned_bytes,
+            plain_bytes
+        );
+
+        // Objects with different keys share nothing, even the keys they have in common.
+        let first = interned[0].as_object().unwrap().keys().next().unwrap();
+        let second = interned[1].as_object().unwrap().keys().nth(1).unwrap();
+        assert_eq!((first.as_str(), second.as_str()), ("id", "id"));
+        assert_ne!(first.as_ptr(), second.as_ptr());
+    }
+
+    #[test]
+    fn modifying_one_object_leaves_others() {
+        let mut pool = KeyPool::new();
+        let mut first = pool.parse(r#"{"a": 1, "b": 2}"#).unwrap();
+        let second = pool.parse(r#"{"b": 3, "a": 4}"#).unwrap();
+        first
+            .as_object_mut()
+            .unwrap()
+            .insert("c".to_owned(), Value::Null);
+        first.as_object_mut().unwrap().remove("a");
+        assert_eq!(first, serde_json::json!({"b": 2, "c": null}));
+        assert_eq!(second, serde_json::json!({"a": 4, "b": 3}));
+        assert_eq!(second.as_object().unwrap().validate_invariants(), Ok(()));
+    }
+}
@@ -1,10 +1,108 @@
 use librrb::{Iter as VIter, IterMut as VIterMut, Vector};
 use serde_json::{Number as JsonNumber, Value as JsonValue};
 use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
 use std::fmt::{self, Debug, Display};
+use std::io;
 use std::iter::FusedIterator;
 use std::mem;
 use std::ops;
+use std::ptr;
+
+#[cfg(feature = "arena")]
+mod arena;
+#[cfg(feature = "tokio")]
+mod async_io;
+#[cfg(feature = "base64")]
+mod base64;
+#[cfg(feature = "bson")]
+mod bson;
+mod canonical;
+mod cursor;
+mod diff;
+mod display;
+mod extract;
+#[cfg(feature = "hash")]
+mod hash;
+#[cfg(feature = "intern")]
+mod intern;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+mod ndjson;
+mod oplog;
+mod pointer;
+mod query;
+#[cfg(feature = "schema")]
+mod schema;
+mod ser;
+mod strict;
+mod tokenizer;
+mod transform;
+mod visit;
+#[cfg(feature = "yaml")]
+mod yaml;
+
+#[cfg(feature = "bson")]
+pub use crate::bson::BsonError;
+#[cfg(feature = "arena")]
+pub use arena::ArenaValue;
+pub use cursor::Cursor;
+pub use diff::DiffSummary;
+pub use extract::{ExtractError, FromValue};
+#[cfg(feature = "intern")]
+pub use intern::KeyPool;
+#[cfg(feature = "msgpack")]
+pub use msgpack::MsgpackError;
+pub use ndjson::NdjsonReader;
+pub use oplog::{Op, OpLog};
+pub use pointer::MissingPathError;
+#[cfg(feature = "schema")]
+pub use schema::ValidationError;
+pub use strict::ParseOptions;
+pub use tokenizer::{Token, Tokenizer};
+pub use transform::TransformError;
+pub use visit::Visitor;
+#[cfg(feature = "yaml")]
+pub use yaml::YamlError;
+
+#[derive(Debug)]
+pub enum ParseError {
+    Io(io::Error),
+    Syntax(serde_json::Error),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => Display::fmt(e, formatter),
+            ParseError::Syntax(e) => Display::fmt(e, formatter),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            ParseError::Syntax(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(e: serde_json::Error) -> Self {
+        ParseError::Syntax(e)
+    }
+}
 
 mod private {
     pub trait Sealed {}
@@ -120,6 +218,7 @@ where
 {
     keys: &'a mut Vector<String>,
     values: &'a mut Vector<Value>,
+    order: Option<&'a mut InsertionOrder>,
     key: String,
     idx: usize,
 }
@@ -130,6 +229,9 @@ impl<'a> VacantEntry<'a> {
     }
 
     pub fn insert(self, value: Value) -> &'a mut Value {
+        if let Some(order) = self.order {
+            order.insert(self.idx);
+        }
         self.keys.insert(self.idx, self.key);
         self.values.insert(self.idx, value);
         self.values.get_mut(self.idx).unwrap()
@@ -139,6 +241,7 @@ impl<'a> VacantEntry<'a> {
 pub struct OccupiedEntry<'a> {
     keys: &'a mut Vector<String>,
     values: &'a mut Vector<Value>,
+    order: Option<&'a mut InsertionOrder>,
     idx: usize,
 }
 
@@ -163,6 +266,9 @@ impl<'a> OccupiedEntry<'a> {
     }
 
     pub fn remove(&mut self) -> Value {
+        if let Some(order) = self.order.as_mut() {
+            order.remove(self.idx);
+        }
         self.keys.remove(self.idx).unwrap();
         self.values.remove(self.idx).unwrap()
     }
@@ -215,6 +321,10 @@ impl<'a> Iterator for Iter<'a> {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.key.size_hint()
+    }
 }
 
 impl<'a> DoubleEndedIterator for Iter<'a> {
@@ -247,6 +357,10 @@ impl<'a> Iterator for IterMut<'a> {
             None
         }
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.key.size_hint()
+    }
 }
 
 impl<'a> DoubleEndedIterator for IterMut<'a> {
@@ -267,10 +381,119 @@ pub type Keys<'a> = VIter<'a, String>;
 pub type Values<'a> = VIter<'a, Value>;
 pub type ValuesMut<'a> = VIterMut<'a, Value>;
 
-#[derive(Clone, Debug, Default, PartialOrd, PartialEq)]
+/// A broken `Object` invariant found by `Object::validate_invariants`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvariantError {
+    /// There are not as many values as keys.
+    LengthMismatch { keys: usize, values: usize },
+    /// The key at `index` sorts before the key preceding it.
+    Unsorted { index: usize },
+    /// The key at `index` is equal to the key preceding it.
+    Duplicate { index: usize },
+}
+
+impl Display for InvariantError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InvariantError::LengthMismatch { keys, values } => {
+                write!(formatter, "object has {} keys but {} values", keys, values)
+            }
+            InvariantError::Unsorted { index } => {
+                write!(formatter, "object key at index {} is out of order", index)
+            }
+            InvariantError::Duplicate { index } => {
+                write!(formatter, "object key at index {} is a duplicate", index)
+            }
+        }
+    }
+}
+
+impl Error for InvariantError {}
+
+/// How `Object::from_iter_with_policy` resolves entries with equal keys.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DupPolicy {
+    LastWins,
+    FirstWins,
+    Error,
+}
+
+/// Returned by `Object::from_iter_with_policy` under `DupPolicy::Error`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DupError {
+    /// The key that appeared more than once.
+    pub key: String,
+}
+
+impl Display for DupError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "duplicate object key \"{}\"", self.key)
+    }
+}
+
+impl Error for DupError {}
+
+/// Returned by `Object::merge_recursive` when the merge would go deeper than allowed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepthError {
+    pub max_depth: usize,
+}
+
+impl Display for DepthError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "merge exceeds the maximum depth of {}",
+            self.max_depth
+        )
+    }
+}
+
+impl Error for DepthError {}
+
+/// A map from strings to values. Keys are kept sorted in ascending order without duplicates, with
+/// each value stored at the same index as its key.
+#[derive(Clone, Debug, Default)]
 pub struct Object {
     keys: Vector<String>,
     values: Vector<Value>,
+    /// Set by `remove_unordered` while the keys may be out of order, cleared by `repair`.
+    dirty: bool,
+    order: Option<InsertionOrder>,
+}
+
+/// The order in which the keys of an object were inserted, as an ordinal stored at the same index
+/// as each key. Later insertions get larger ordinals.
+#[derive(Clone, Debug, Default)]
+struct InsertionOrder {
+    ordinals: Vector<u64>,
+    next: u64,
+}
+
+impl InsertionOrder {
+    fn insert(&mut self, idx: usize) {
+        self.ordinals.insert(idx, self.next);
+        self.next += 1;
+    }
+
+    fn remove(&mut self, idx: usize) {
+        self.ordinals.remove(idx);
+    }
+}
+
+/// Objects are equal when they hold the same entries, regardless of how they were built.
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        self.keys == other.keys && self.values == other.values
+    }
+}
+
+/// Objects are ordered like sorted maps, comparing `(key, value)` entries in key order and then by
+/// length, as `BTreeMap` does.
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Object) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
 }
 
 impl Object {
@@ -278,18 +501,95 @@ impl Object {
         Object {
             keys: Vector::new(),
             values: Vector::new(),
+            dirty: false,
+            order: None,
+        }
+    }
+
+    /// Creates an empty object that also remembers the order in which keys are inserted, see
+    /// `iter_insertion_order`. The order is kept up to date by `insert`, `remove`, `entry`,
+    /// `get_or_insert_with`, `retain`, `append`, `remove_unordered`, `repair`, `rename_keys` and
+    /// `trim_keys`. Methods that return a separate object, such as `intersect_keys`, return one
+    /// that does not track insertion order.
+    pub fn with_insertion_order() -> Self {
+        Object {
+            order: Some(InsertionOrder::default()),
+            ..Object::new()
         }
     }
 
+    pub fn tracks_insertion_order(&self) -> bool {
+        self.order.is_some()
+    }
+
+    /// Iterates over the entries in the order their keys were first inserted, or in key order if
+    /// the object does not track insertion order. This sorts the entries on every call.
+    pub fn iter_insertion_order<'a>(
+        &'a self,
+    ) -> impl Iterator<Item = (&'a String, &'a Value)> + 'a {
+        let mut indices: Vec<usize> = (0..self.keys.len()).collect();
+        if let Some(order) = &self.order {
+            indices.sort_by_key(|idx| *order.ordinals.get(*idx).unwrap());
+        }
+        indices
+            .into_iter()
+            .map(move |idx| (self.keys.get(idx).unwrap(), self.values.get(idx).unwrap()))
+    }
+
     pub fn clear(&mut self) {
         unimplemented!()
     }
 
+    /// Starts building an object entry by entry, see `ObjectBuilder`.
+    pub fn builder() -> ObjectBuilder {
+        ObjectBuilder::default()
+    }
+
+    /// Builds an object from entries in any order. Of entries with equal keys, the last one wins.
+    fn from_entries(entries: Vec<(String, Value)>) -> Self {
+        Object::from_iter_with_policy(entries, DupPolicy::LastWins).unwrap()
+    }
+
+    /// Builds an object from entries in any order, sorting them once. Entries with equal keys are
+    /// resolved by `policy`. Under `DupPolicy::Error` the error names the smallest repeated key,
+    /// which need not be the first repeat in `iter`.
+    pub fn from_iter_with_policy<I, K, V>(iter: I, policy: DupPolicy) -> Result<Object, DupError>
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        let mut entries: Vec<(String, Value)> = iter
+            .into_iter()
+            .map(|(key, value)| (key.into(), value.into()))
+            .collect();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut obj = Object::new();
+        for (key, value) in entries {
+            let len = obj.keys.len();
+            if len > 0 && obj.keys.get(len - 1) == Some(&key) {
+                match policy {
+                    DupPolicy::LastWins => *obj.values.get_mut(len - 1).unwrap() = value,
+                    DupPolicy::FirstWins => {}
+                    DupPolicy::Error => return Err(DupError { key }),
+                }
+            } else {
+                obj.keys.push_back(key);
+                obj.values.push_back(value);
+            }
+        }
+        Ok(obj)
+    }
+
     fn get_index_for_key<Q>(&self, key: &Q) -> Result<usize, usize>
     where
         String: Borrow<Q>,
         Q: Ord + ?Sized,
     {
+        debug_assert!(
+            !self.dirty,
+            "object lookup after remove_unordered without repair"
+        );
         match self.keys.equal_range(key) {
             Ok(range) => {
                 debug_assert_eq!(range.len(), 1);
@@ -319,6 +619,107 @@ impl Object {
             .and_then(move |v| self.values.get_mut(v))
     }
 
+    /// Whether any value equals `value`. This scans every value, so it takes linear time.
+    pub fn contains_value(&self, value: &Value) -> bool {
+        self.values.iter().any(|v| v == value)
+    }
+
+    /// The smallest key whose value equals `value`. This scans the values, so it takes linear time.
+    pub fn find_key_by_value(&self, value: &Value) -> Option<&String> {
+        self.iter().find(|(_, v)| *v == value).map(|(key, _)| key)
+    }
+
+    /// Iterates over the entries with their index in the sorted keys, as `position_of` reports it.
+    pub fn iter_indexed(&self) -> impl Iterator<Item = (usize, &String, &Value)> {
+        self.iter()
+            .enumerate()
+            .map(|(idx, (key, value))| (idx, key, value))
+    }
+
+    /// The entries ordered by comparing their keys with `compare`, instead of lexicographically.
+    /// The sort is stable and happens on every call.
+    pub fn iter_sorted_by<F>(&self, mut compare: F) -> Vec<(&String, &Value)>
+    where
+        F: FnMut(&str, &str) -> Ordering,
+    {
+        let mut entries: Vec<(&String, &Value)> = self.iter().collect();
+        entries.sort_by(|a, b| compare(a.0, b.0));
+        entries
+    }
+
+    /// Binary searches the sorted keys with a comparator, as `slice::binary_search_by` does. `f`
+    /// reports how each key it is given orders relative to the target, and must be consistent with
+    /// the key order. Returns `Ok` with the index of a matching key, or `Err` with the index where
+    /// such a key would be inserted.
+    pub fn binary_search_by<F>(&self, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(&str) -> Ordering,
+    {
+        let mut low = 0;
+        let mut high = self.keys.len();
+        while low < high {
+            let mid = low + (high - low) / 2;
+            match f(self.keys.get(mid).unwrap()) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+
+    /// The index of `key` in the sorted keys, which is also the index of its value in the vectors
+    /// returned by `as_parallel`.
+    pub fn position_of<Q: ?Sized>(&self, key: &Q) -> Option<usize>
+    where
+        String: Borrow<Q>,
+        Q: Ord,
+    {
+        self.get_index_for_key(key).ok()
+    }
+
+    /// The entry with the smallest key.
+    pub fn first(&self) -> Option<(&String, &Value)> {
+        Some((self.keys.get(0)?, self.values.get(0)?))
+    }
+
+    /// The entry with the largest key.
+    pub fn last(&self) -> Option<(&String, &Value)> {
+        let idx = self.keys.len().checked_sub(1)?;
+        Some((self.keys.get(idx)?, self.values.get(idx)?))
+    }
+
+    /// Returns mutable references to the values of several distinct keys at once, with `None` for
+    /// absent keys. Panics if the same key is requested more than once. Each value is reached as
+    /// `get_mut` reaches it, so only the paths to the requested values are copied.
+    pub fn get_disjoint_mut<Q, const N: usize>(&mut self, keys: [&Q; N]) -> [Option<&mut Value>; N]
+    where
+        String: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        let mut indices = [None; N];
+        for (slot, key) in indices.iter_mut().zip(keys.iter()) {
+            *slot = self.get_index_for_key(*key).ok();
+        }
+        for (i, idx) in indices.iter().enumerate() {
+            if idx.is_some() && indices[..i].contains(idx) {
+                panic!("duplicate key passed to get_disjoint_mut");
+            }
+        }
+
+        let mut result = [(); N].map(|_| None);
+        for (slot, idx) in result.iter_mut().zip(indices.iter()) {
+            if let Some(idx) = *idx {
+                let value: *mut Value = self.values.get_mut(idx).unwrap();
+                // SAFETY: the indices are distinct, so no two references alias. Once `get_mut` has
+                // made the path to a value unique, later calls find its leaf already unique and
+                // leave it in place, so earlier pointers stay valid for the borrow of `self`.
+                *slot = Some(unsafe { &mut *value });
+            }
+        }
+        result
+    }
+
     pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
     where
         String: Borrow<Q>,
@@ -335,6 +736,9 @@ impl Object {
                 Some(mem::replace(existing_value_ref, v))
             }
             Err(position) => {
+                if let Some(order) = &mut self.order {
+                    order.insert(position);
+                }
                 self.keys.insert(position, k);
                 self.values.insert(position, v);
                 None
@@ -350,6 +754,9 @@ impl Object {
         let position = self.get_index_for_key(key);
         match position {
             Ok(position) => {
+                if let Some(order) = &mut self.order {
+                    order.remove(position);
+                }
                 self.keys.remove(position);
                 self.values.remove(position)
             }
@@ -357,12 +764,182 @@ impl Object {
         }
     }
 
+    /// Removes a key by moving the last entry into its place, which is cheaper than `remove` but
+    /// leaves the keys out of order.
+    ///
+    /// **The object is broken until `repair` is called.** Lookups, insertions and removals other
+    /// than further calls to this method rely on the keys being sorted, and give wrong results
+    /// (or panic in debug builds) until then. This is only meant for tearing down many entries at
+    /// once. While the object is broken this method finds keys with a linear scan.
+    pub fn remove_unordered<Q: ?Sized>(&mut self, key: &Q) -> Option<Value>
+    where
+        String: Borrow<Q>,
+        Q: Ord + Eq,
+    {
+        let position = if self.dirty {
+            self.keys.iter().position(|k| k.borrow() == key)?
+        } else {
+            self.get_index_for_key(key).ok()?
+        };
+        let last = self.keys.len() - 1;
+        let last_key = self.keys.remove(last).unwrap();
+        let last_value = self.values.remove(last).unwrap();
+        let last_ordinal = self
+            .order
+            .as_mut()
+            .map(|order| order.ordinals.remove(last).unwrap());
+        if position == last {
+            return Some(last_value);
+        }
+        self.dirty = true;
+        if let (Some(order), Some(ordinal)) = (&mut self.order, last_ordinal) {
+            *order.ordinals.get_mut(position).unwrap() = ordinal;
+        }
+        *self.keys.get_mut(position).unwrap() = last_key;
+        Some(mem::replace(
+            self.values.get_mut(position).unwrap(),
+            last_value,
+        ))
+    }
+
     pub fn append(&mut self, other: &mut Self) {
-        let other_keys = mem::replace(&mut other.keys, Vector::new());
-        let other_values = mem::replace(&mut other.values, Vector::new());
-        self.keys.append(other_keys);
-        self.values.append(other_values);
-        self.keys.dual_sort(&mut self.values)
+        if self.order.is_some() {
+            let other = mem::replace(other, Object::new());
+            for (key, value) in other.iter_insertion_order() {
+                self.insert(key.clone(), value.clone());
+            }
+            return;
+        }
+        let other = mem::replace(other, Object::new());
+        *self = self.merge_sorted(&other, |_, _, right| right);
+    }
+
+    /// Moves the entries of `other` into `self`. For a key present in both, the entry becomes
+    /// `f(key, self_value, other_value)`.
+    pub fn merge_with<F>(&mut self, other: Object, mut f: F)
+    where
+        F: FnMut(&str, Value, Value) -> Value,
+    {
+        if self.order.is_some() {
+            for (key, value) in other.iter_insertion_order() {
+                match self.get_mut(key.as_str()) {
+                    Some(slot) => {
+                        let current = mem::replace(slot, Value::Null);
+                        *slot = f(key, current, value.clone());
+                    }
+                    None => {
+                        self.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+            return;
+        }
+        *self = self.merge_sorted(&other, f);
+    }
+
+    /// Merges `other` into `self`. Where both hold an object under the same key the two are merged
+    /// in turn, otherwise the entry from `other` replaces the one in `self`. `self` and `other`
+    /// are at depth 1, and needing to merge objects nested more than `max_depth` levels deep fails
+    /// with `self` left unchanged, so the recursion stays bounded whatever the input.
+    pub fn merge_recursive(&mut self, other: Object, max_depth: usize) -> Result<(), DepthError> {
+        let mut merged = self.clone();
+        merged.merge_at_depth(other, 1, max_depth)?;
+        *self = merged;
+        Ok(())
+    }
+
+    fn merge_at_depth(
+        &mut self,
+        other: Object,
+        depth: usize,
+        max_depth: usize,
+    ) -> Result<(), DepthError> {
+        if depth > max_depth {
+            return Err(DepthError { max_depth });
+        }
+        for (key, value) in other.iter() {
+            match (self.get_mut(key), value) {
+                (Some(Value::Object(existing)), Value::Object(patch)) => {
+                    existing.merge_at_depth(patch.clone(), depth + 1, max_depth)?
+                }
+                (Some(existing), _) => *existing = value.clone(),
+                (None, _) => {
+                    self.insert(key.clone(), value.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Merges two objects in a single pass over their sorted keys. Keys found in both are combined
+    /// with `combine(key, left, right)`. The result does not track insertion order.
+    fn merge_sorted<F>(&self, other: &Object, mut combine: F) -> Object
+    where
+        F: FnMut(&str, Value, Value) -> Value,
+    {
+        let mut merged = Object::new();
+        let mut left = self.iter().peekable();
+        let mut right = other.iter().peekable();
+        loop {
+            let order = match (left.peek(), right.peek()) {
+                (Some((l, _)), Some((r, _))) => l.cmp(r),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            let (key, value) = match order {
+                Ordering::Less => {
+                    let (key, value) = left.next().unwrap();
+                    (key, value.clone())
+                }
+                Ordering::Greater => {
+                    let (key, value) = right.next().unwrap();
+                    (key, value.clone())
+                }
+                Ordering::Equal => {
+                    let (key, l) = left.next().unwrap();
+                    let (_, r) = right.next().unwrap();
+                    (key, combine(key, l.clone(), r.clone()))
+                }
+            };
+            merged.keys.push_back(key.clone());
+            merged.values.push_back(value);
+        }
+        merged
+    }
+
+    /// Appends `other` by concatenating the backing vectors, without sorting or merging.
+    ///
+    /// Every key in `other` must be greater than every key in `self`. This is only checked in
+    /// debug builds. Breaking the rule is not undefined behaviour, but leaves the keys unsorted so
+    /// lookups give wrong results until `repair` is called.
+    pub fn append_sorted_unchecked(&mut self, other: Object) {
+        debug_assert!(
+            match (self.keys.len(), other.keys.get(0)) {
+                (len, Some(first)) if len > 0 => self.keys.get(len - 1).unwrap() < first,
+                _ => true,
+            },
+            "append_sorted_unchecked with overlapping keys"
+        );
+        if let Some(order) = &mut self.order {
+            let offset = order.next;
+            match &other.order {
+                Some(other_order) => {
+                    for ordinal in other_order.ordinals.iter() {
+                        order.ordinals.push_back(offset + ordinal);
+                    }
+                    order.next += other_order.next;
+                }
+                None => {
+                    for ordinal in 0..other.len() as u64 {
+                        order.ordinals.push_back(offset + ordinal);
+                    }
+                    order.next += other.len() as u64;
+                }
+            }
+        }
+        self.keys.append(other.keys);
+        self.values.append(other.values);
     }
 
     pub fn entry<S>(&mut self, key: S) -> Entry
@@ -376,16 +953,86 @@ impl Object {
                 idx,
                 keys: &mut self.keys,
                 values: &mut self.values,
+                order: self.order.as_mut(),
             }),
             Err(idx) => Entry::Vacant(VacantEntry {
                 idx,
                 key: string,
                 keys: &mut self.keys,
                 values: &mut self.values,
+                order: self.order.as_mut(),
+            }),
+        }
+    }
+
+    /// Like `entry`, but takes a borrowed key and only copies it into a `String` if the entry is
+    /// vacant.
+    pub fn entry_ref<Q>(&mut self, key: &Q) -> Entry
+    where
+        String: Borrow<Q>,
+        Q: Ord + ToOwned<Owned = String> + ?Sized,
+    {
+        match self.get_index_for_key(key) {
+            Ok(idx) => Entry::Occupied(OccupiedEntry {
+                idx,
+                keys: &mut self.keys,
+                values: &mut self.values,
+                order: self.order.as_mut(),
+            }),
+            Err(idx) => Entry::Vacant(VacantEntry {
+                idx,
+                key: key.to_owned(),
+                keys: &mut self.keys,
+                values: &mut self.values,
+                order: self.order.as_mut(),
             }),
         }
     }
 
+    /// Returns the value at `key`, inserting the result of `f` if the key is absent. Unlike
+    /// `entry`, the key is only copied into a `String` when it is inserted.
+    pub fn get_or_insert_with<F>(&mut self, key: &str, f: F) -> &mut Value
+    where
+        F: FnOnce() -> Value,
+    {
+        let idx = match self.get_index_for_key(key) {
+            Ok(idx) => idx,
+            Err(idx) => {
+                if let Some(order) = &mut self.order {
+                    order.insert(idx);
+                }
+                self.keys.insert(idx, key.to_owned());
+                self.values.insert(idx, f());
+                idx
+            }
+        };
+        self.values.get_mut(idx).unwrap()
+    }
+
+    /// Returns the array stored at `key`, inserting an empty array if the key is absent. A value of
+    /// any other type at `key` is replaced by an empty array.
+    pub fn array_entry(&mut self, key: &str) -> &mut Vector<Value> {
+        let value = self
+            .entry(key)
+            .or_insert_with(|| Value::Array(Vector::new()));
+        if !value.is_array() {
+            *value = Value::Array(Vector::new());
+        }
+        value.as_array_mut().unwrap()
+    }
+
+    /// Returns the object stored at `key`, inserting an empty object if the key is absent. A value
+    /// of any other type at `key` is replaced by an empty object.
+    pub fn object_entry(&mut self, key: &str) -> &mut Object {
+        let value = self
+            .entry(key)
+            .or_insert_with(|| Value::Object(Object::new()));
+        if !value.is_object() {
+            *value = Value::Object(Object::new());
+        }
+        value.as_object_mut().unwrap()
+    }
+
     pub fn len(&self) -> usize {
         self.keys.len()
     }
@@ -418,84 +1065,553 @@ impl Object {
     pub fn values_mut(&mut self) -> ValuesMut {
         self.values.iter_mut()
     }
-}
 
-#[derive(Clone, PartialEq, PartialOrd)]
-pub enum Number {
-    PosInt(u64),
-    /// Always less than zero.
-    NegInt(i64),
-    /// Always finite.
-    Float(f64),
-}
+    /// Consumes the object, yielding its keys in sorted order.
+    pub fn into_keys(self) -> impl Iterator<Item = String> {
+        drain_front(self.keys)
+    }
 
-impl Number {
-    pub fn is_i64(&self) -> bool {
-        match self {
-            Number::PosInt(v) => *v <= i64::max_value() as u64,
-            Number::NegInt(_) => true,
-            Number::Float(_) => false,
-        }
+    /// Consumes the object, yielding its values in the order of their keys.
+    pub fn into_values(self) -> impl Iterator<Item = Value> {
+        drain_front(self.values)
     }
 
-    pub fn is_u64(&self) -> bool {
-        match self {
-            Number::PosInt(_) => true,
-            Number::NegInt(_) | Number::Float(_) => false,
+    /// Checks that the keys are strictly increasing and that every key has a value.
+    pub fn validate_invariants(&self) -> Result<(), InvariantError> {
+        if self.keys.len() != self.values.len() {
+            return Err(InvariantError::LengthMismatch {
+                keys: self.keys.len(),
+                values: self.values.len(),
+            });
+        }
+        let mut previous: Option<&String> = None;
+        for (index, key) in self.keys.iter().enumerate() {
+            if let Some(previous) = previous {
+                match previous.cmp(key) {
+                    Ordering::Less => {}
+                    Ordering::Equal => return Err(InvariantError::Duplicate { index }),
+                    Ordering::Greater => return Err(InvariantError::Unsorted { index }),
+                }
+            }
+            previous = Some(key);
         }
+        Ok(())
     }
 
-    pub fn is_f64(&self) -> bool {
-        match self {
-            Number::Float(_) => true,
-            Number::PosInt(_) | Number::NegInt(_) => false,
-        }
+    /// Restores the invariants by sorting the entries and removing duplicate keys. Of duplicates
+    /// the entry stored last wins, and keys or values without a partner are dropped.
+    pub fn repair(&mut self) {
+        *self = self
+            .rebuild_renamed(String::clone, DupPolicy::LastWins)
+            .unwrap();
     }
 
-    pub fn as_i64(&self) -> Option<i64> {
-        match self {
-            Number::PosInt(n) => {
-                if *n <= i64::max_value() as u64 {
-                    Some(*n as i64)
-                } else {
-                    None
+    /// Rebuilds the object with every key replaced by `rename(key)`, sorting the entries once.
+    /// Entries that end up with equal keys are resolved by `policy`, where the last entry is the
+    /// one stored last. The entries kept keep their place in the insertion order.
+    fn rebuild_renamed<F>(&self, mut rename: F, policy: DupPolicy) -> Result<Object, DupError>
+    where
+        F: FnMut(&String) -> String,
+    {
+        let mut entries: Vec<(String, usize)> = self
+            .keys
+            .iter()
+            .zip(self.values.iter())
+            .enumerate()
+            .map(|(idx, (key, _))| (rename(key), idx))
+            .collect();
+        // The sort is stable, so entries with equal keys stay in the order they are stored.
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let mut obj = Object {
+            order: self.order.as_ref().map(|order| InsertionOrder {
+                ordinals: Vector::new(),
+                next: order.next,
+            }),
+            ..Object::new()
+        };
+        for (key, idx) in entries {
+            let value = self.values.get(idx).unwrap().clone();
+            let ordinal = self
+                .order
+                .as_ref()
+                .map(|order| order.ordinals.get(idx).copied().unwrap_or(order.next));
+            let len = obj.keys.len();
+            if len > 0 && obj.keys.get(len - 1) == Some(&key) {
+                match policy {
+                    DupPolicy::LastWins => {
+                        *obj.values.get_mut(len - 1).unwrap() = value;
+                        if let (Some(order), Some(ordinal)) = (&mut obj.order, ordinal) {
+                            *order.ordinals.get_mut(len - 1).unwrap() = ordinal;
+                        }
+                    }
+                    DupPolicy::FirstWins => {}
+                    DupPolicy::Error => return Err(DupError { key }),
+                }
+            } else {
+                obj.keys.push_back(key);
+                obj.values.push_back(value);
+                if let (Some(order), Some(ordinal)) = (&mut obj.order, ordinal) {
+                    order.ordinals.push_back(ordinal);
                 }
             }
-            Number::NegInt(n) => Some(*n),
-            Number::Float(_) => None,
         }
+        Ok(obj)
     }
 
-    pub fn as_u64(&self) -> Option<u64> {
-        match self {
-            Number::PosInt(n) => Some(*n),
-            Number::NegInt(_) | Number::Float(_) => None,
+    /// Keeps only the entries for which `f` returns true.
+    pub fn retain<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&String, &mut Value) -> bool,
+    {
+        let keys = mem::replace(&mut self.keys, Vector::new());
+        let mut values = mem::replace(&mut self.values, Vector::new());
+        let ordinals = match &mut self.order {
+            Some(order) => mem::replace(&mut order.ordinals, Vector::new()),
+            None => Vector::new(),
+        };
+        for (idx, (key, value)) in keys.iter().zip(values.iter_mut()).enumerate() {
+            if f(key, value) {
+                self.keys.push_back(key.clone());
+                self.values.push_back(value.clone());
+                if let Some(order) = &mut self.order {
+                    order.ordinals.push_back(*ordinals.get(idx).unwrap());
+                }
+            }
         }
     }
 
-    pub fn as_f64(&self) -> Option<f64> {
-        match self {
-            Number::PosInt(n) => Some(*n as f64),
-            Number::NegInt(n) => Some(*n as f64),
-            Number::Float(n) => Some(*n),
+    /// Applies `f` to every value, stopping at the first error. The keys are kept as they are, so
+    /// only the values are rebuilt.
+    pub fn map_values<F, E>(self, mut f: F) -> Result<Object, E>
+    where
+        F: FnMut(Value) -> Result<Value, E>,
+    {
+        let mut values = Vector::new();
+        for value in self.values.iter() {
+            values.push_back(f(value.clone())?);
         }
+        Ok(Object {
+            keys: self.keys,
+            values,
+            dirty: self.dirty,
+            order: self.order,
+        })
     }
 
-    pub fn from_f64(f: f64) -> Option<Number> {
+    /// Renames every key for which `f` returns a new name, keeping the others. If several keys end
+    /// up with the same name, the entry whose original key sorted last is kept.
+    pub fn rename_keys<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str) -> Option<String>,
+    {
+        *self = self
+            .rebuild_renamed(
+                |key| f(key).unwrap_or_else(|| key.clone()),
+                DupPolicy::LastWins,
+            )
+            .unwrap();
+    }
+
+    /// Renames every key found in `mapping` to the name it maps to, keeping the others. Keys that
+    /// end up equal are resolved by `policy`, where the last entry is the one whose original key
+    /// sorted last. On error `self` is left unchanged.
+    pub fn rekey(
+        &mut self,
+        mapping: &HashMap<String, String>,
+        policy: DupPolicy,
+    ) -> Result<(), DupError> {
+        *self = self.rebuild_renamed(|key| mapping.get(key).unwrap_or(key).clone(), policy)?;
+        Ok(())
+    }
+
+    /// Trims ASCII whitespace from both ends of every key. Keys that collide once trimmed are
+    /// resolved as in `rename_keys`.
+    pub fn trim_keys(&mut self) {
+        self.rename_keys(|key| {
+            let trimmed = key.trim_matches(|c: char| c.is_ascii_whitespace());
+            if trimmed.len() == key.len() {
+                None
+            } else {
+                Some(trimmed.to_owned())
+            }
+        });
+    }
+
+    /// The sorted keys of the object.
+    pub fn keys_vector(&self) -> &Vector<String> {
+        &self.keys
+    }
+
+    /// Returns true if both objects have exactly the same keys, regardless of their values.
+    pub fn keys_equal(&self, other: &Object) -> bool {
+        self.keys == other.keys
+    }
+
+    /// Returns the keys only present in `self` and the keys only present in `other`, both sorted.
+    pub fn key_diff<'a>(&'a self, other: &'a Object) -> (Vec<&'a String>, Vec<&'a String>) {
+        let mut only_self = Vec::new();
+        let mut only_other = Vec::new();
+        let mut left = self.keys.iter().peekable();
+        let mut right = other.keys.iter().peekable();
+        loop {
+            match (left.peek(), right.peek()) {
+                (Some(l), Some(r)) => match l.cmp(r) {
+                    Ordering::Less => only_self.push(left.next().unwrap()),
+                    Ordering::Greater => only_other.push(right.next().unwrap()),
+                    Ordering::Equal => {
+                        left.next();
+                        right.next();
+                    }
+                },
+                (Some(_), None) => only_self.push(left.next().unwrap()),
+                (None, Some(_)) => only_other.push(right.next().unwrap()),
+                (None, None) => break,
+            }
+        }
+        (only_self, only_other)
+    }
+
+    /// The entries of `self` whose keys also appear in `other`.
+    pub fn intersect_keys(&self, other: &Object) -> Object {
+        self.filter_by_keys(other, true)
+    }
+
+    /// The entries of `self` whose keys do not appear in `other`.
+    pub fn difference_keys(&self, other: &Object) -> Object {
+        self.filter_by_keys(other, false)
+    }
+
+    /// A new object holding the entries for those of `keys` that are present. The values are cheap
+    /// clones that share their storage with `self`.
+    pub fn clone_subset(&self, keys: &[&str]) -> Object {
+        let mut keys = keys.to_vec();
+        keys.sort_unstable();
+        keys.dedup();
+        let mut subset = Object::new();
+        for key in keys {
+            if let Ok(idx) = self.get_index_for_key(key) {
+                subset.keys.push_back(key.to_owned());
+                subset
+                    .values
+                    .push_back(self.values.get(idx).unwrap().clone());
+            }
+        }
+        subset
+    }
+
+    /// Keeps the entries of `self` whose presence in `other` matches `shared`, walking both sorted
+    /// key vectors once.
+    fn filter_by_keys(&self, other: &Object, shared: bool) -> Object {
+        let mut result = Object::new();
+        let mut right = other.keys.iter().peekable();
+        for (key, value) in self.iter() {
+            while right.peek().map_or(false, |r| *r < key) {
+                right.next();
+            }
+            if (right.peek() == Some(&key)) == shared {
+                result.keys.push_back(key.clone());
+                result.values.push_back(value.clone());
+            }
+        }
+        result
+    }
+
+    /// The sorted keys and their values. The two vectors are index-aligned: the value at any index
+    /// belongs to the key at that index.
+    pub fn as_parallel(&self) -> (&Vector<String>, &Vector<Value>) {
+        (&self.keys, &self.values)
+    }
+}
+
+/// Merges two objects into a new one, taking the right-hand value for keys present in both.
+impl ops::Add for Object {
+    type Output = Object;
+
+    fn add(self, other: Object) -> Object {
+        &self + &other
+    }
+}
+
+impl<'a> ops::Add<&'a Object> for &'a Object {
+    type Output = Object;
+
+    fn add(self, other: &Object) -> Object {
+        self.merge_sorted(other, |_, _, right| right)
+    }
+}
+
+/// Moves the elements out of a vector from front to back. Elements are only cloned where their
+/// storage is shared with another vector.
+fn drain_front<A: Clone + Debug>(mut vector: Vector<A>) -> impl Iterator<Item = A> {
+    std::iter::from_fn(move || {
+        if vector.len() == 0 {
+            None
+        } else {
+            vector.remove(0)
+        }
+    })
+}
+
+/// Collects entries for an `Object` and sorts them once in `build`, instead of inserting each one
+/// into sorted position. Of entries with equal keys, the last one set wins.
+#[derive(Clone, Debug, Default)]
+pub struct ObjectBuilder {
+    entries: Vec<(String, Value)>,
+}
+
+impl ObjectBuilder {
+    pub fn set<K, V>(mut self, key: K, value: V) -> Self
+    where
+        K: Into<String>,
+        V: Into<Value>,
+    {
+        self.entries.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn build(self) -> Object {
+        Object::from_entries(self.entries)
+    }
+}
+
+/// Selects which object entries `Value::prune` removes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PruneOptions {
+    pub nulls: bool,
+    pub empty_arrays: bool,
+    pub empty_objects: bool,
+}
+
+impl PruneOptions {
+    fn removes(&self, value: &Value) -> bool {
+        match value {
+            Value::Null => self.nulls,
+            Value::Array(arr) => self.empty_arrays && arr.len() == 0,
+            Value::Object(obj) => self.empty_objects && obj.is_empty(),
+            _ => false,
+        }
+    }
+}
+
+/// Size measurements of a document, see `Value::metrics`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DocMetrics {
+    /// Every value in the document, including the root.
+    pub nodes: usize,
+    /// The number of nested arrays and objects on the deepest path, zero for a scalar.
+    pub max_depth: usize,
+    pub object_keys: usize,
+    pub array_elements: usize,
+}
+
+#[derive(Clone, PartialEq, PartialOrd)]
+pub enum Number {
+    PosInt(u64),
+    /// Always less than zero.
+    NegInt(i64),
+    /// Always finite.
+    Float(f64),
+    /// The exact text of a number that none of the other variants can hold without losing
+    /// precision, such as `1e400` or an integer beyond 64 bits.
+    #[cfg(feature = "arbitrary_precision")]
+    Big(String),
+}
+
+/// Splits decimal text into its sign, its significant digits and the exponent of the last digit,
+/// so two texts denote the same value exactly when their parts are equal.
+#[cfg(feature = "arbitrary_precision")]
+fn decimal_parts(s: &str) -> Option<(bool, String, i64)> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, s),
+    };
+    let (mantissa, mut exponent) = match s.find(|c| c == 'e' || c == 'E') {
+        Some(idx) => (&s[..idx], s[idx + 1..].parse::<i64>().ok()?),
+        None => (s, 0),
+    };
+    let (int, frac) = match mantissa.find('.') {
+        Some(idx) => (&mantissa[..idx], &mantissa[idx + 1..]),
+        None => (mantissa, ""),
+    };
+    exponent -= frac.len() as i64;
+    let digits = format!("{}{}", int, frac);
+    let digits = digits.trim_start_matches('0');
+    let significant = digits.trim_end_matches('0');
+    if significant.is_empty() {
+        return Some((false, String::new(), 0));
+    }
+    exponent += (digits.len() - significant.len()) as i64;
+    Some((negative, significant.to_owned(), exponent))
+}
+
+/// Parses decimal text as a double, but only if the double denotes exactly the same value.
+#[cfg(feature = "arbitrary_precision")]
+fn exact_f64(s: &str) -> Option<f64> {
+    let f = s.parse::<f64>().ok().filter(|f| f.is_finite())?;
+    if decimal_parts(&format!("{:e}", f)) == decimal_parts(s) {
+        Some(f)
+    } else {
+        None
+    }
+}
+
+/// Under `arbitrary_precision`, serde_json hands every number to `Visitor::visit_map` as a map
+/// with this single key, whose value is the text of the number.
+#[cfg(feature = "arbitrary_precision")]
+pub(crate) const NUMBER_TOKEN: &str = "$serde_json::private::Number";
+
+/// Converts the text of a number received under `NUMBER_TOKEN`.
+#[cfg(feature = "arbitrary_precision")]
+pub(crate) fn number_from_token<E: serde::de::Error>(text: &str) -> Result<Number, E> {
+    text.parse::<JsonNumber>()
+        .map(Number::from)
+        .map_err(E::custom)
+}
+
+impl Number {
+    pub fn is_i64(&self) -> bool {
+        match self {
+            Number::PosInt(v) => *v <= i64::max_value() as u64,
+            Number::NegInt(_) => true,
+            Number::Float(_) => false,
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(_) => false,
+        }
+    }
+
+    pub fn is_u64(&self) -> bool {
+        match self {
+            Number::PosInt(_) => true,
+            Number::NegInt(_) | Number::Float(_) => false,
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(_) => false,
+        }
+    }
+
+    pub fn is_f64(&self) -> bool {
+        match self {
+            Number::Float(_) => true,
+            Number::PosInt(_) | Number::NegInt(_) => false,
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(_) => false,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            Number::PosInt(n) => {
+                if *n <= i64::max_value() as u64 {
+                    Some(*n as i64)
+                } else {
+                    None
+                }
+            }
+            Number::NegInt(n) => Some(*n),
+            Number::Float(_) => None,
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => s.parse().ok(),
+        }
+    }
+
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Number::PosInt(n) => Some(*n),
+            Number::NegInt(_) | Number::Float(_) => None,
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => s.parse().ok(),
+        }
+    }
+
+    /// For a `Big` number, returns `None` unless a double holds its exact value.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Number::PosInt(n) => Some(*n as f64),
+            Number::NegInt(n) => Some(*n as f64),
+            Number::Float(n) => Some(*n),
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => exact_f64(s),
+        }
+    }
+
+    /// The nearest double, which for a `Big` number may be infinite.
+    pub(crate) fn to_f64_lossy(&self) -> f64 {
+        match self {
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => s.parse().unwrap(),
+            n => n.as_f64().unwrap(),
+        }
+    }
+
+    pub fn from_f64(f: f64) -> Option<Number> {
         if f.is_finite() {
             Some(Number::Float(f))
         } else {
             None
         }
     }
+
+    /// Moves a non-negative `NegInt` into `PosInt`. The constructors and conversions in this crate
+    /// already uphold this, so this is only needed for numbers built directly from the variants.
+    pub fn normalize(&mut self) {
+        if let Number::NegInt(n) = *self {
+            if n >= 0 {
+                *self = Number::PosInt(n as u64);
+            }
+        }
+    }
+
+    /// The integer equal to `f`, if `f` is integral and fits in a `u64` or an `i64`.
+    fn from_integral_f64(f: f64) -> Option<Number> {
+        // 2^64 and -2^63 are exact as doubles, unlike u64::MAX and i64::MAX.
+        if f.fract() != 0.0 {
+            None
+        } else if f >= 0.0 && f < 18_446_744_073_709_551_616.0 {
+            Some(Number::PosInt(f as u64))
+        } else if f < 0.0 && f >= -9_223_372_036_854_775_808.0 {
+            Some(Number::NegInt(f as i64))
+        } else {
+            None
+        }
+    }
+
+    /// Orders numbers by value, comparing them as doubles with `f64::total_cmp`. Numbers that
+    /// round to the same double are then ordered floats first, integers by exact value, and `Big`
+    /// numbers by text.
+    fn total_cmp(&self, other: &Number) -> Ordering {
+        let exact = |n: &Number| match n {
+            Number::PosInt(u) => (1, i128::from(*u)),
+            Number::NegInt(i) => (1, i128::from(*i)),
+            Number::Float(_) => (0, 0),
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(_) => (2, 0),
+        };
+        let ordering = self
+            .to_f64_lossy()
+            .total_cmp(&other.to_f64_lossy())
+            .then_with(|| exact(self).cmp(&exact(other)));
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            if let (Number::Big(a), Number::Big(b)) = (self, other) {
+                return ordering.then_with(|| a.cmp(b));
+            }
+        }
+        ordering
+    }
 }
 
+/// Numbers are written as `serde_json` writes them, so floats use the shortest representation that
+/// round-trips and always show that they are floats, as in `1.0` or `1e20`.
 impl fmt::Display for Number {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Number::PosInt(u) => Display::fmt(&u, formatter),
             Number::NegInt(i) => Display::fmt(&i, formatter),
-            Number::Float(f) => Display::fmt(&f, formatter),
+            Number::Float(f) => match JsonNumber::from_f64(*f) {
+                Some(n) => Display::fmt(&n, formatter),
+                None => Display::fmt(&f, formatter),
+            },
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => Display::fmt(&s, formatter),
         }
     }
 }
@@ -513,6 +1629,10 @@ impl Debug for Number {
             Number::Float(f) => {
                 debug.field(&f);
             }
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => {
+                debug.field(&s);
+            }
         }
         debug.finish()
     }
@@ -520,6 +1640,16 @@ impl Debug for Number {
 
 impl From<JsonNumber> for Number {
     fn from(n: JsonNumber) -> Number {
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            if !n.is_u64() && !n.is_i64() {
+                let text = n.to_string();
+                return match exact_f64(&text) {
+                    Some(f) => Number::Float(f),
+                    None => Number::Big(text),
+                };
+            }
+        }
         if n.is_f64() {
             Number::Float(n.as_f64().unwrap())
         } else if n.is_u64() {
@@ -530,6 +1660,71 @@ impl From<JsonNumber> for Number {
     }
 }
 
+impl From<u64> for Number {
+    fn from(n: u64) -> Number {
+        Number::PosInt(n)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Number {
+        if n < 0 {
+            Number::NegInt(n)
+        } else {
+            Number::PosInt(n as u64)
+        }
+    }
+}
+
+impl From<u32> for Number {
+    fn from(n: u32) -> Number {
+        Number::from(u64::from(n))
+    }
+}
+
+impl From<i32> for Number {
+    fn from(n: i32) -> Number {
+        Number::from(i64::from(n))
+    }
+}
+
+/// Returned when converting a `Number` with no serde_json equivalent, such as a non-finite float.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnrepresentableNumber(pub Number);
+
+impl Display for UnrepresentableNumber {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{} cannot be represented as a JSON number",
+            self.0
+        )
+    }
+}
+
+impl Error for UnrepresentableNumber {}
+
+/// Converts without going through `f64`, so integers keep their exact value.
+impl<'a> TryFrom<&'a Number> for JsonNumber {
+    type Error = UnrepresentableNumber;
+
+    fn try_from(n: &'a Number) -> Result<JsonNumber, UnrepresentableNumber> {
+        let converted = match n {
+            Number::PosInt(u) => Some((*u).into()),
+            Number::NegInt(i) => Some((*i).into()),
+            Number::Float(f) => JsonNumber::from_f64(*f),
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => s.parse().ok(),
+        };
+        converted.ok_or_else(|| UnrepresentableNumber(n.clone()))
+    }
+}
+
+/// A JSON value. Cloning is O(1): the arrays and objects of a clone share librrb vector nodes
+/// with the original. Writes are copy-on-write, so a write through `get_mut`, `insert` or
+/// `remove` copies only the shared nodes on the path to the element it touches and leaves
+/// siblings shared. A clone of a snapshot can therefore be modified freely without the snapshot
+/// changing.
 #[derive(Clone, Debug, PartialOrd, PartialEq)]
 pub enum Value {
     Null,
@@ -541,6 +1736,57 @@ pub enum Value {
 }
 
 impl Value {
+    /// A total order on values. Values of different types are ordered null, number, string,
+    /// boolean, array, object, as `PartialOrd` orders them. Numbers are compared by value, even
+    /// across `PosInt`, `NegInt` and `Float`, and a NaN built from `Number::Float` sorts above
+    /// every other number. Arrays are compared element by element and objects entry by entry, in
+    /// key order.
+    pub fn total_cmp(&self, other: &Value) -> Ordering {
+        let rank = |value: &Value| match value {
+            Value::Null => 0,
+            Value::Number(_) => 1,
+            Value::String(_) => 2,
+            Value::Bool(_) => 3,
+            Value::Array(_) => 4,
+            Value::Object(_) => 5,
+        };
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => {
+                for (x, y) in a.iter().zip(b.iter()) {
+                    match x.total_cmp(y) {
+                        Ordering::Equal => {}
+                        ordering => return ordering,
+                    }
+                }
+                a.len().cmp(&b.len())
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                for ((ka, va), (kb, vb)) in a.iter().zip(b.iter()) {
+                    match ka.cmp(kb).then_with(|| va.total_cmp(vb)) {
+                        Ordering::Equal => {}
+                        ordering => return ordering,
+                    }
+                }
+                a.len().cmp(&b.len())
+            }
+            _ => rank(self).cmp(&rank(other)),
+        }
+    }
+
+    pub(crate) fn type_name(&self) -> &'static str {
+        match self {
+            Value::Null => "null",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::Bool(_) => "boolean",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        }
+    }
+
     pub fn is_null(&self) -> bool {
         match self {
             Value::Null => true,
@@ -562,6 +1808,20 @@ impl Value {
         }
     }
 
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    pub fn as_number_mut(&mut self) -> Option<&mut Number> {
+        match self {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
     pub fn is_string(&self) -> bool {
         match self {
             Value::String(_) => true,
@@ -576,6 +1836,19 @@ impl Value {
         }
     }
 
+    /// Replaces a string holding a JSON number with that number, returning whether it did so.
+    pub fn coerce_number(&mut self) -> bool {
+        let number = match self {
+            Value::String(s) => match serde_json::from_str::<JsonNumber>(s) {
+                Ok(n) => Number::from(n),
+                Err(_) => return false,
+            },
+            _ => return false,
+        };
+        *self = Value::Number(number);
+        true
+    }
+
     pub fn is_boolean(&self) -> bool {
         match self {
             Value::Bool(_) => true,
@@ -590,6 +1863,20 @@ impl Value {
         }
     }
 
+    /// JavaScript-like truthiness. `null`, `false`, zero (including `-0.0`), the empty string, the
+    /// empty array and the empty object are falsy and everything else is truthy. Unlike
+    /// JavaScript, empty containers are falsy.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => n.as_f64() != Some(0.0),
+            Value::String(s) => !s.is_empty(),
+            Value::Array(arr) => arr.len() > 0,
+            Value::Object(obj) => !obj.is_empty(),
+        }
+    }
+
     pub fn is_array(&self) -> bool {
         match self {
             Value::Array(_) => true,
@@ -611,6 +1898,46 @@ impl Value {
         }
     }
 
+    pub fn array_len(&self) -> Option<usize> {
+        self.as_array().map(Vector::len)
+    }
+
+    /// The elements of an array as `i64`s, or `None` if any element is not an integer in range.
+    pub fn as_i64_vec(&self) -> Option<Vec<i64>> {
+        self.as_array()?
+            .iter()
+            .map(|item| item.as_number()?.as_i64())
+            .collect()
+    }
+
+    /// The elements of an array as `f64`s. Integers are converted, any other element gives `None`.
+    pub fn as_f64_vec(&self) -> Option<Vec<f64>> {
+        self.as_array()?
+            .iter()
+            .map(|item| item.as_number()?.as_f64())
+            .collect()
+    }
+
+    pub fn as_string_vec(&self) -> Option<Vec<String>> {
+        self.as_array()?
+            .iter()
+            .map(|item| item.as_str().map(str::to_owned))
+            .collect()
+    }
+
+    /// Joins an array of strings with `sep` between them. Returns `None` if `self` is not an array
+    /// or any element is not a string.
+    pub fn join(&self, sep: &str) -> Option<String> {
+        let mut joined = String::new();
+        for (idx, item) in self.as_array()?.iter().enumerate() {
+            if idx > 0 {
+                joined.push_str(sep);
+            }
+            joined.push_str(item.as_str()?);
+        }
+        Some(joined)
+    }
+
     pub fn is_object(&self) -> bool {
         match self {
             Value::Object(_) => true,
@@ -631,42 +1958,700 @@ impl Value {
             _ => None,
         }
     }
-}
 
-impl Default for Value {
-    fn default() -> Self {
-        Value::Null
+    /// The keys of an object, in the order `Object::keys` yields them.
+    pub fn object_keys(&self) -> Option<Vec<&String>> {
+        Some(self.as_object()?.keys().collect())
     }
-}
 
-impl From<JsonValue> for Value {
-    fn from(v: JsonValue) -> Value {
-        match v {
-            JsonValue::Null => Value::Null,
-            JsonValue::Number(n) => Value::Number(n.into()),
-            JsonValue::String(s) => Value::String(s),
-            JsonValue::Bool(b) => Value::Bool(b),
-            JsonValue::Array(arr) => {
-                let mut v = Vector::new();
-                for item in arr {
-                    v.push_back(item.into())
-                }
-                Value::Array(v)
+    /// Takes the array out of the value, or gives the value back if it is not an array.
+    pub fn try_into_array(self) -> Result<Vector<Value>, Value> {
+        match self {
+            Value::Array(arr) => Ok(arr),
+            other => Err(other),
+        }
+    }
+
+    /// Takes the object out of the value, or gives the value back if it is not an object.
+    pub fn try_into_object(self) -> Result<Object, Value> {
+        match self {
+            Value::Object(obj) => Ok(obj),
+            other => Err(other),
+        }
+    }
+
+    /// Walks a `.`-separated path of object keys, creating missing entries as `Null` along the way.
+    /// Any value on the path that is not an object, including `self`, is overwritten with an empty
+    /// object.
+    pub fn entry_at(&mut self, path: &str) -> &mut Value {
+        let mut current = self;
+        for key in path.split('.') {
+            if !current.is_object() {
+                *current = Value::Object(Object::new());
             }
-            JsonValue::Object(obj) => {
-                let mut o = Object::new();
-                for (k, v) in obj {
-                    o.insert(k, v.into());
-                }
-                Value::Object(o)
+            current = current
+                .as_object_mut()
+                .unwrap()
+                .entry(key)
+                .or_insert(Value::Null);
+        }
+        current
+    }
+
+    /// Walks a `.`-separated path of object keys as `entry_at` does, returning `default` if any
+    /// key along the way is missing or any value on the path is not an object.
+    pub fn get_or<'a>(&'a self, path: &str, default: &'a Value) -> &'a Value {
+        let mut current = self;
+        for key in path.split('.') {
+            match current.as_object().and_then(|obj| obj.get(key)) {
+                Some(next) => current = next,
+                None => return default,
             }
         }
+        current
     }
-}
+
+    /// An empty array. The persistent vector grows in fixed-size nodes and cannot reserve space, so
+    /// the capacity is accepted for symmetry with `Vec::with_capacity` but otherwise ignored.
+    pub fn new_array(_capacity: usize) -> Value {
+        Value::Array(Vector::new())
+    }
+
+    pub fn new_object() -> Value {
+        Value::Object(Object::new())
+    }
+
+    /// Same as `from_array_iter`.
+    pub fn array_of<I>(iter: I) -> Value
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        Value::from_array_iter(iter)
+    }
+
+    pub fn from_array_iter<I>(iter: I) -> Value
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        let mut arr = Vector::new();
+        for item in iter {
+            arr.push_back(item);
+        }
+        Value::Array(arr)
+    }
+
+    /// Parses a JSON document. Despite the name nothing is borrowed from `s`: strings and keys are
+    /// copied out of it. A `Value` has no lifetime parameter, and the librrb nodes holding arrays
+    /// and objects are shared between clones that can outlive `s`, so every node has to own its
+    /// contents. This is the entry point a borrowing variant would replace.
+    pub fn from_str_borrowed(s: &str) -> Result<Value, ParseError> {
+        Ok(serde_json::from_str::<JsonValue>(s)?.into())
+    }
+
+    /// Removes object entries selected by `opts` throughout the document. Children are pruned
+    /// before their parents, so an object emptied by pruning can itself be removed. Array elements
+    /// are pruned internally but never removed, and `self` is never removed.
+    pub fn prune(&mut self, opts: PruneOptions) {
+        match self {
+            Value::Array(arr) => {
+                for item in arr.iter_mut() {
+                    item.prune(opts);
+                }
+            }
+            Value::Object(obj) => obj.retain(|_, value| {
+                value.prune(opts);
+                !opts.removes(value)
+            }),
+            _ => {}
+        }
+    }
+
+    /// Merges `patch` into `self`. Where both sides are objects they are merged key by key,
+    /// otherwise the patch value, including `null`, replaces what is in `self`. Only the objects
+    /// along the patched paths are copied: values the patch does not mention keep sharing storage
+    /// with earlier clones of `self`.
+    pub fn deep_merge(&mut self, patch: Value) {
+        match (self, patch) {
+            (Value::Object(obj), Value::Object(patch)) => {
+                for (key, value) in patch.iter() {
+                    match obj.get_mut(key) {
+                        Some(existing) => existing.deep_merge(value.clone()),
+                        None => {
+                            obj.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+            (this, patch) => *this = patch,
+        }
+    }
+
+    /// Merges `other` into `self`, treating arrays as lists of records identified by `id_field`.
+    /// Objects are merged key by key. An element of an array in `other` is merged into the first
+    /// object element of `self` with an equal `id_field`, and appended if there is none or if it
+    /// is not an object with that field. Anything else in `other` replaces what is in `self`.
+    /// Matching elements is a linear search, so merging two arrays takes quadratic time.
+    pub fn merge_arrays_by_key(&mut self, other: &Value, id_field: &str) {
+        match (self, other) {
+            (Value::Object(obj), Value::Object(other)) => {
+                for (key, value) in other.iter() {
+                    match obj.get_mut(key) {
+                        Some(existing) => existing.merge_arrays_by_key(value, id_field),
+                        None => {
+                            obj.insert(key.clone(), value.clone());
+                        }
+                    }
+                }
+            }
+            (Value::Array(arr), Value::Array(other)) => {
+                for item in other.iter() {
+                    let id = item.as_object().and_then(|obj| obj.get(id_field));
+                    let existing = id.and_then(|id| {
+                        arr.iter_mut().find(|existing| {
+                            existing.as_object().and_then(|obj| obj.get(id_field)) == Some(id)
+                        })
+                    });
+                    match existing {
+                        Some(existing) => existing.merge_arrays_by_key(item, id_field),
+                        None => arr.push_back(item.clone()),
+                    }
+                }
+            }
+            (this, other) => *this = other.clone(),
+        }
+    }
+
+    /// Flattens the top `max_depth` levels of nesting into one object. Each key joins the object
+    /// keys and array indices leading to its value with `.`, so keys have at most `max_depth`
+    /// segments, and whatever lies deeper is kept whole at the flattened key. Empty arrays and
+    /// objects below the root are kept as values. With a depth of zero, or for a scalar, the result
+    /// holds `self` under the empty key.
+    pub fn flatten_to_depth(&self, max_depth: usize) -> Object {
+        let mut entries = Vec::new();
+        let mut stack = vec![(String::new(), self, 0)];
+        while let Some((key, value, depth)) = stack.pop() {
+            let join = |segment: &str| {
+                if depth == 0 {
+                    segment.to_owned()
+                } else {
+                    format!("{}.{}", key, segment)
+                }
+            };
+            match value {
+                Value::Array(arr) if depth < max_depth && (depth == 0 || arr.len() > 0) => {
+                    for (idx, item) in arr.iter().enumerate() {
+                        stack.push((join(&idx.to_string()), item, depth + 1));
+                    }
+                }
+                Value::Object(obj) if depth < max_depth && (depth == 0 || !obj.is_empty()) => {
+                    for (child_key, child) in obj.iter() {
+                        stack.push((join(child_key), child, depth + 1));
+                    }
+                }
+                _ => entries.push((key, value.clone())),
+            }
+        }
+        Object::from_entries(entries)
+    }
+
+    /// Projects the fields named in `selection`, an object whose keys name the fields to keep. A
+    /// field selected with `true` or `null` is kept whole, a field selected with an object is
+    /// projected by that object in turn, and a field selected with anything else is dropped.
+    /// Selected fields that are missing are omitted. A selection applies to each element of an
+    /// array, and a selection that is not an object, or a scalar being selected from, is returned
+    /// whole.
+    pub fn select(&self, selection: &Value) -> Value {
+        let fields = match selection {
+            Value::Object(fields) => fields,
+            _ => return self.clone(),
+        };
+        match self {
+            Value::Object(obj) => {
+                let mut result = Object::new();
+                for (key, field) in fields.iter() {
+                    let value = match (obj.get(key.as_str()), field) {
+                        (Some(value), Value::Object(_)) => value.select(field),
+                        (Some(value), Value::Bool(true)) | (Some(value), Value::Null) => {
+                            value.clone()
+                        }
+                        _ => continue,
+                    };
+                    result.keys.push_back(key.clone());
+                    result.values.push_back(value);
+                }
+                Value::Object(result)
+            }
+            Value::Array(arr) => arr.iter().map(|item| item.select(selection)).collect(),
+            _ => self.clone(),
+        }
+    }
+
+    /// Calls `f` on every scalar in the document, in document order, allowing it to be replaced in
+    /// place. Arrays and objects are walked but not passed to `f`.
+    pub fn map_scalars<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&mut Value),
+    {
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            match value {
+                Value::Array(arr) => stack.extend(arr.iter_mut().rev()),
+                Value::Object(obj) => stack.extend(obj.values_mut().rev()),
+                _ => f(value),
+            }
+        }
+    }
+
+    /// Sorts every array in the document, inner arrays before the arrays holding them, so two
+    /// documents that differ only in the order of array elements become equal. Elements are
+    /// ordered by `Value::total_cmp`.
+    pub fn sort_all_arrays(&mut self) {
+        let mut items: Vec<Value> = match self {
+            Value::Array(arr) => {
+                for item in arr.iter_mut() {
+                    item.sort_all_arrays();
+                }
+                arr.iter().cloned().collect()
+            }
+            Value::Object(obj) => {
+                for value in obj.values_mut() {
+                    value.sort_all_arrays();
+                }
+                return;
+            }
+            _ => return,
+        };
+        items.sort_by(Value::total_cmp);
+        *self = Value::from_array_iter(items);
+    }
+
+    /// Converts every float with an integral value that fits in a `u64` or an `i64` into that
+    /// integer, so `5.0` becomes `5`. Other floats, such as `5.5` or `1e300`, are left alone.
+    pub fn normalize_numbers(&mut self) {
+        self.map_scalars(|value| {
+            if let Value::Number(n) = value {
+                if let Number::Float(f) = *n {
+                    if let Some(int) = Number::from_integral_f64(f) {
+                        *n = int;
+                    }
+                }
+            }
+        });
+    }
+
+    /// Clamps every number into `[min, max]`, comparing them as doubles. A number out of range
+    /// becomes the nearer bound: an integer if the number was an integer and the bound is an
+    /// integer that fits in a `u64` or an `i64`, and a float otherwise. Numbers in range are left
+    /// as they are.
+    ///
+    /// Panics if `min` is greater than `max` or either is not finite.
+    pub fn clamp_numbers(&mut self, min: f64, max: f64) {
+        assert!(
+            min.is_finite() && max.is_finite() && min <= max,
+            "invalid clamp range [{}, {}]",
+            min,
+            max
+        );
+        self.map_scalars(|value| {
+            if let Value::Number(n) = value {
+                let f = n.to_f64_lossy();
+                let bound = if f < min {
+                    min
+                } else if f > max {
+                    max
+                } else {
+                    return;
+                };
+                let integral = match n {
+                    Number::Float(_) => false,
+                    #[cfg(feature = "arbitrary_precision")]
+                    Number::Big(s) => !s.contains(|c| c == '.' || c == 'e' || c == 'E'),
+                    _ => true,
+                };
+                *n = Some(bound)
+                    .filter(|_| integral)
+                    .and_then(Number::from_integral_f64)
+                    .unwrap_or(Number::Float(bound));
+            }
+        });
+    }
+
+    /// Calls `f` on every node of the document in document order, parents before their children,
+    /// and replaces each node for which it returns `Some`. A replacement is not walked into.
+    pub fn replace_all<F>(&mut self, f: F)
+    where
+        F: FnMut(&Value) -> Option<Value>,
+    {
+        self.replace_nodes(false, f)
+    }
+
+    /// Like `replace_all`, but only passes leaves to `f`. As for `leaf_paths`, empty arrays and
+    /// objects count as leaves.
+    pub fn replace_all_leaves<F>(&mut self, f: F)
+    where
+        F: FnMut(&Value) -> Option<Value>,
+    {
+        self.replace_nodes(true, f)
+    }
+
+    fn replace_nodes<F>(&mut self, leaves_only: bool, mut f: F)
+    where
+        F: FnMut(&Value) -> Option<Value>,
+    {
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            let is_leaf = match value {
+                Value::Array(arr) => arr.len() == 0,
+                Value::Object(obj) => obj.is_empty(),
+                _ => true,
+            };
+            if is_leaf || !leaves_only {
+                if let Some(replacement) = f(value) {
+                    *value = replacement;
+                    continue;
+                }
+            }
+            match value {
+                Value::Array(arr) => stack.extend(arr.iter_mut().rev()),
+                Value::Object(obj) => stack.extend(obj.values_mut().rev()),
+                _ => {}
+            }
+        }
+    }
+
+    pub fn metrics(&self) -> DocMetrics {
+        let mut metrics = DocMetrics::default();
+        let mut stack = vec![(self, 0)];
+        while let Some((value, depth)) = stack.pop() {
+            metrics.nodes += 1;
+            match value {
+                Value::Array(arr) => {
+                    metrics.max_depth = metrics.max_depth.max(depth + 1);
+                    metrics.array_elements += arr.len();
+                    stack.extend(arr.iter().map(|item| (item, depth + 1)));
+                }
+                Value::Object(obj) => {
+                    metrics.max_depth = metrics.max_depth.max(depth + 1);
+                    metrics.object_keys += obj.len();
+                    stack.extend(obj.values().map(|item| (item, depth + 1)));
+                }
+                _ => {}
+            }
+        }
+        metrics
+    }
+
+    /// Counts the nodes in the document, including `self`, that satisfy `pred`.
+    pub fn count_matching<F>(&self, pred: F) -> usize
+    where
+        F: Fn(&Value) -> bool,
+    {
+        self.count_nodes(false, pred)
+    }
+
+    /// Like `count_matching`, but only checks leaves. As for `leaf_paths`, empty arrays and objects
+    /// count as leaves.
+    pub fn count_matching_leaves<F>(&self, pred: F) -> usize
+    where
+        F: Fn(&Value) -> bool,
+    {
+        self.count_nodes(true, pred)
+    }
+
+    fn count_nodes<F>(&self, leaves_only: bool, pred: F) -> usize
+    where
+        F: Fn(&Value) -> bool,
+    {
+        let mut count = 0;
+        let mut stack = vec![self];
+        while let Some(value) = stack.pop() {
+            let children = stack.len();
+            match value {
+                Value::Array(arr) => stack.extend(arr.iter()),
+                Value::Object(obj) => stack.extend(obj.values()),
+                _ => {}
+            }
+            let is_leaf = stack.len() == children;
+            if (is_leaf || !leaves_only) && pred(value) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Whether `self` is contained in `other`: every entry of an object must be present in the
+    /// corresponding object of `other`, which may have more, and is compared in the same way.
+    /// Arrays and scalars must be equal, so an array is not matched against a longer one.
+    pub fn is_subset_of(&self, other: &Value) -> bool {
+        let mut stack = vec![(self, other)];
+        while let Some((part, whole)) = stack.pop() {
+            match (part, whole) {
+                (Value::Object(part), Value::Object(whole)) => {
+                    for (key, value) in part.iter() {
+                        match whole.get(key.as_str()) {
+                            Some(other) => stack.push((value, other)),
+                            None => return false,
+                        }
+                    }
+                }
+                _ if part == whole => {}
+                _ => return false,
+            }
+        }
+        true
+    }
+
+    /// Equality that compares numbers by their numeric value, so `5`, `5.0` and `5e0` are equal
+    /// wherever they appear. Everything else is compared as by `==`.
+    pub fn loose_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => loose_number_eq(a, b),
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a.loose_eq(b))
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                a.keys_equal(b)
+                    && a.values
+                        .iter()
+                        .zip(b.values.iter())
+                        .all(|(a, b)| a.loose_eq(b))
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    /// Returns true if both values are arrays or objects whose elements are all stored in the same
+    /// shared nodes, as happens after a cheap clone, so the two are equal without comparing them.
+    /// This looks at the address of every element but not inside them. Empty arrays and objects,
+    /// which have no elements to share, and other values are only pointer-equal to themselves.
+    pub fn ptr_eq(&self, other: &Value) -> bool {
+        match (self, other) {
+            _ if ptr::eq(self, other) => true,
+            (Value::Array(a), Value::Array(b)) => same_storage(a, b),
+            (Value::Object(a), Value::Object(b)) => {
+                same_storage(&a.keys, &b.keys) && same_storage(&a.values, &b.values)
+            }
+            _ => false,
+        }
+    }
+
+    /// Copies every node of the value into fresh storage so that nothing is shared with `self`.
+    /// This costs time and memory linear in the size of the document, unlike `clone` which is O(1).
+    pub fn deep_clone_detached(&self) -> Value {
+        match self {
+            Value::Array(arr) => Value::from_array_iter(arr.iter().map(Value::deep_clone_detached)),
+            Value::Object(obj) => {
+                let mut detached = Object::new();
+                for (key, value) in obj.iter() {
+                    detached.keys.push_back(key.clone());
+                    detached.values.push_back(value.deep_clone_detached());
+                }
+                Value::Object(detached)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Appends the items to the end of this array. Returns false, leaving the value untouched, if
+    /// this is not an array.
+    pub fn array_extend<I>(&mut self, iter: I) -> bool
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        match self {
+            Value::Array(arr) => {
+                for item in iter {
+                    arr.push_back(item);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Compares an integer with a float exactly, without rounding the integer to a double.
+fn loose_number_eq(a: &Number, b: &Number) -> bool {
+    match (a, b) {
+        (Number::Float(f), Number::PosInt(u)) | (Number::PosInt(u), Number::Float(f)) => {
+            f.fract() == 0.0 && *f >= 0.0 && *f < 18446744073709551616.0 && *f as u64 == *u
+        }
+        (Number::Float(f), Number::NegInt(i)) | (Number::NegInt(i), Number::Float(f)) => {
+            f.fract() == 0.0 && *f < 0.0 && *f >= -9223372036854775808.0 && *f as i64 == *i
+        }
+        (a, b) => a == b,
+    }
+}
+
+/// A write copies only the nodes on the path to the element it touches, so a clone edited in the
+/// middle still shares its first and last elements: every element has to be checked.
+fn same_storage<T>(a: &Vector<T>, b: &Vector<T>) -> bool {
+    a.len() == b.len() && a.get(0).is_some() && a.iter().zip(b.iter()).all(|(a, b)| ptr::eq(a, b))
+}
+
+impl Default for Value {
+    fn default() -> Self {
+        Value::Null
+    }
+}
+
+impl std::iter::FromIterator<Value> for Value {
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = Value>,
+    {
+        Value::from_array_iter(iter)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Value {
+        Value::Bool(b)
+    }
+}
+
+impl From<Number> for Value {
+    fn from(n: Number) -> Value {
+        Value::Number(n)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Value {
+        Value::Number(n.into())
+    }
+}
+
+/// A non-finite float becomes `Null`, as in `serde_json`.
+impl From<f64> for Value {
+    fn from(f: f64) -> Value {
+        Number::from_f64(f).map_or(Value::Null, Value::Number)
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Value {
+        Value::String(s)
+    }
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Value {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<Object> for Value {
+    fn from(obj: Object) -> Value {
+        Value::Object(obj)
+    }
+}
+
+impl From<JsonValue> for Value {
+    fn from(v: JsonValue) -> Value {
+        match v {
+            JsonValue::Null => Value::Null,
+            JsonValue::Number(n) => Value::Number(n.into()),
+            JsonValue::String(s) => Value::String(s),
+            JsonValue::Bool(b) => Value::Bool(b),
+            JsonValue::Array(arr) => {
+                let mut v = Vector::new();
+                for item in arr {
+                    v.push_back(item.into())
+                }
+                Value::Array(v)
+            }
+            JsonValue::Object(obj) => {
+                let mut o = Object::new();
+                for (k, v) in obj {
+                    o.insert(k, v.into());
+                }
+                Value::Object(o)
+            }
+        }
+    }
+}
+
+/// Compares structurally, with numbers compared by their numeric value as in `Value::loose_eq`.
+impl PartialEq<JsonValue> for Value {
+    fn eq(&self, other: &JsonValue) -> bool {
+        match (self, other) {
+            (Value::Null, JsonValue::Null) => true,
+            (Value::Bool(a), JsonValue::Bool(b)) => a == b,
+            (Value::Number(a), JsonValue::Number(b)) => loose_number_eq(a, &b.clone().into()),
+            (Value::String(a), JsonValue::String(b)) => a == b,
+            (Value::Array(a), JsonValue::Array(b)) => {
+                a.len() == b.len() && a.iter().zip(b.iter()).all(|(a, b)| a == b)
+            }
+            (Value::Object(a), JsonValue::Object(b)) => {
+                a.len() == b.len()
+                    && b.iter()
+                        .all(|(key, b)| a.get(key.as_str()).map_or(false, |a| a == b))
+            }
+            _ => false,
+        }
+    }
+}
+
+impl PartialEq<Value> for JsonValue {
+    fn eq(&self, other: &Value) -> bool {
+        other == self
+    }
+}
+
+impl<'a> From<&'a Number> for JsonValue {
+    fn from(n: &'a Number) -> JsonValue {
+        JsonNumber::try_from(n).map_or(JsonValue::Null, JsonValue::Number)
+    }
+}
+
+impl<'a> From<&'a Value> for JsonValue {
+    fn from(v: &'a Value) -> JsonValue {
+        match v {
+            Value::Null => JsonValue::Null,
+            Value::Number(n) => n.into(),
+            Value::String(s) => JsonValue::String(s.clone()),
+            Value::Bool(b) => JsonValue::Bool(*b),
+            Value::Array(arr) => JsonValue::Array(arr.iter().map(JsonValue::from).collect()),
+            Value::Object(obj) => {
+                let mut o = serde_json::Map::new();
+                for (k, v) in obj.iter() {
+                    o.insert(k.clone(), v.into());
+                }
+                JsonValue::Object(o)
+            }
+        }
+    }
+}
+
+impl From<Value> for JsonValue {
+    fn from(v: Value) -> JsonValue {
+        JsonValue::from(&v)
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
     use serde_json::json;
     #[test]
     fn it_works() {
@@ -692,4 +2677,1471 @@ mod tests {
         let values: Value = json!({}).into();
         assert!(values.eq(&Value::Object(Object::new())));
     }
+
+    #[test]
+    fn entry_at_creates_path() {
+        let mut value = Value::Object(Object::new());
+        *value.entry_at("a.b.c") = Value::Bool(true);
+        let expected: Value = json!({"a": {"b": {"c": true}}}).into();
+        assert_eq!(value, expected);
+
+        assert_eq!(*value.entry_at("a.b.d"), Value::Null);
+        let expected: Value = json!({"a": {"b": {"c": true, "d": null}}}).into();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn entry_at_overwrites_non_objects() {
+        let mut value: Value = json!({"a": 5}).into();
+        *value.entry_at("a.b") = Value::Bool(false);
+        let expected: Value = json!({"a": {"b": false}}).into();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn get_or_falls_back_to_default() {
+        let value: Value = json!({"server": {"port": 8080, "tls": null}, "name": "api"}).into();
+        let default = Value::from(443u64);
+        assert_eq!(value.get_or("server.port", &default), &Value::from(8080u64));
+        assert_eq!(value.get_or("server.tls", &default), &Value::Null);
+        assert_eq!(value.get_or("server.timeout", &default), &default);
+        assert_eq!(value.get_or("name.first", &default), &default);
+        assert_eq!(Value::Null.get_or("server", &default), &default);
+    }
+
+    #[test]
+    fn number_from_integers() {
+        assert_eq!(Number::from(-1i64), Number::NegInt(-1));
+        assert_eq!(Number::from(0i64), Number::PosInt(0));
+        assert_eq!(Number::from(7i64), Number::PosInt(7));
+        assert_eq!(
+            Number::from(u64::max_value()),
+            Number::PosInt(u64::max_value())
+        );
+        assert_eq!(Number::from(-3i32), Number::NegInt(-3));
+        assert_eq!(Number::from(3u32), Number::PosInt(3));
+    }
+
+    #[test]
+    fn number_normalize() {
+        let mut n = Number::NegInt(3);
+        n.normalize();
+        assert_eq!(n, Number::PosInt(3));
+        n.normalize();
+        assert_eq!(n, Number::PosInt(3));
+
+        let mut n = Number::NegInt(-3);
+        n.normalize();
+        assert_eq!(n, Number::NegInt(-3));
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn big_numbers_round_trip() {
+        let texts = [
+            "1e400",
+            "1234567890123456789012345678901234567890",
+            "0.10000000000000000001",
+        ];
+        for text in texts.iter() {
+            let value = Value::from_str_borrowed(text).unwrap();
+            let n = Number::Big(text.to_string());
+            assert_eq!(value, Value::Number(n.clone()));
+            assert_eq!((n.as_i64(), n.as_u64(), n.as_f64()), (None, None, None));
+            assert_eq!(
+                serde_json::to_string(&JsonValue::from(&value)).unwrap(),
+                *text
+            );
+        }
+
+        let exact = Value::from_str_borrowed("[2.50, 1e2, 100000000000000000000]").unwrap();
+        let expected: Value = json!([2.5, 100.0, 1e20]).into();
+        assert_eq!(exact, expected);
+    }
+
+    #[test]
+    fn converts_to_serde_json_numbers_exactly() {
+        let max = JsonNumber::try_from(&Number::PosInt(u64::max_value())).unwrap();
+        assert_eq!(max.as_u64(), Some(u64::max_value()));
+        assert_eq!(max, JsonNumber::from(u64::max_value()));
+        assert_eq!(Number::from(max), Number::PosInt(u64::max_value()));
+
+        let min = JsonNumber::try_from(&Number::NegInt(i64::min_value())).unwrap();
+        assert_eq!(min, JsonNumber::from(i64::min_value()));
+        let float = JsonNumber::try_from(&Number::Float(0.1)).unwrap();
+        assert_eq!(float, JsonNumber::from_f64(0.1).unwrap());
+
+        let err = JsonNumber::try_from(&Number::Float(f64::INFINITY)).unwrap_err();
+        assert_eq!(err, UnrepresentableNumber(Number::Float(f64::INFINITY)));
+        assert!(JsonNumber::try_from(&Number::Float(f64::NAN)).is_err());
+    }
+
+    #[test]
+    fn deep_merge_keeps_untouched_subtrees_shared() {
+        let big = Value::from_array_iter((0..1000u64).map(|n| json!({"n": n}).into()));
+        let mut value: Value = json!({"small": {"x": 1, "y": {"z": 2}}, "other": [1]}).into();
+        value["big"] = big;
+        let before = value.clone();
+
+        value.deep_merge(json!({"small": {"y": {"w": 3}, "v": null}, "other": "replaced"}).into());
+
+        assert!(value["big"].ptr_eq(&before["big"]));
+        assert_eq!(value["big"], before["big"]);
+        assert_eq!(
+            value["small"],
+            json!({"x": 1, "y": {"z": 2, "w": 3}, "v": null})
+        );
+        assert_eq!(value["other"], json!("replaced"));
+        assert_eq!(before["other"], json!([1]));
+    }
+
+    #[test]
+    fn merge_arrays_by_key_matches_records() {
+        let mut value: Value = json!({
+            "name": "team",
+            "members": [
+                {"id": 1, "name": "ann", "roles": {"admin": true}},
+                {"id": 2, "name": "bob"},
+                "guest"
+            ]
+        })
+        .into();
+        let patch: Value = json!({
+            "members": [
+                {"id": 2, "name": "robert", "email": "bob@example.com"},
+                {"id": 3, "name": "cat"},
+                {"id": 1, "roles": {"owner": true}},
+                {"name": "no id"},
+                "guest"
+            ],
+            "size": 3
+        })
+        .into();
+        value.merge_arrays_by_key(&patch, "id");
+        let expected: Value = json!({
+            "name": "team",
+            "members": [
+                {"id": 1, "name": "ann", "roles": {"admin": true, "owner": true}},
+                {"id": 2, "name": "robert", "email": "bob@example.com"},
+                "guest",
+                {"id": 3, "name": "cat"},
+                {"name": "no id"},
+                "guest"
+            ],
+            "size": 3
+        })
+        .into();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn flattens_to_depth() {
+        let value: Value =
+            json!({"a": {"b": {"c": 1}, "d": [1, {"e": 2}], "g": {}}, "f": 3}).into();
+
+        let flat = Value::Object(value.flatten_to_depth(1));
+        assert_eq!(flat, value);
+
+        let flat = value.flatten_to_depth(2);
+        assert_eq!(flat.validate_invariants(), Ok(()));
+        let expected: Value =
+            json!({"a.b": {"c": 1}, "a.d": [1, {"e": 2}], "a.g": {}, "f": 3}).into();
+        assert_eq!(Value::Object(flat.clone()), expected);
+        assert!(flat.get("a.d").unwrap().ptr_eq(&value["a"]["d"]));
+
+        let flat = value.flatten_to_depth(3);
+        let expected: Value =
+            json!({"a.b.c": 1, "a.d.0": 1, "a.d.1": {"e": 2}, "a.g": {}, "f": 3}).into();
+        assert_eq!(Value::Object(flat), expected);
+
+        let flat = value.flatten_to_depth(0);
+        assert_eq!(flat.get(""), Some(&value));
+        assert_eq!(flat.len(), 1);
+        assert!(Value::new_object().flatten_to_depth(2).is_empty());
+        assert!(Value::new_array(0).flatten_to_depth(2).is_empty());
+    }
+
+    #[test]
+    fn select_projects_fields() {
+        let value: Value = json!({
+            "id": 7,
+            "name": "ann",
+            "password": "hunter2",
+            "address": {"city": "Cape Town", "street": "Long St", "code": 8001},
+            "friends": [{"id": 1, "name": "bob"}, {"id": 2, "name": "cat"}],
+            "meta": {"created": 1}
+        })
+        .into();
+        let selection: Value = json!({
+            "id": true,
+            "name": null,
+            "password": false,
+            "address": {"city": true, "country": true},
+            "friends": {"name": true},
+            "missing": true,
+            "meta": true
+        })
+        .into();
+        let expected: Value = json!({
+            "id": 7,
+            "name": "ann",
+            "address": {"city": "Cape Town"},
+            "friends": [{"name": "bob"}, {"name": "cat"}],
+            "meta": {"created": 1}
+        })
+        .into();
+        assert_eq!(value.select(&selection), expected);
+        assert_eq!(value.select(&Value::Null), value);
+    }
+
+    #[test]
+    fn loose_equality_compares_numbers_by_value() {
+        let ints: Value = json!({"a": [5, -5], "b": 0}).into();
+        let floats: Value = json!({"a": [5.0, -5.0], "b": -0.0}).into();
+        assert_ne!(ints, floats);
+        assert!(ints.loose_eq(&floats));
+        assert!(floats.loose_eq(&ints));
+
+        let five = Value::Number(5.into());
+        assert!(!five.loose_eq(&json!(5.0000001).into()));
+        assert!(!five.loose_eq(&json!("5").into()));
+        assert!(!ints.loose_eq(&json!({"a": [5, -5], "c": 0}).into()));
+
+        let big = Value::Number(Number::PosInt((1 << 53) + 1));
+        assert!(!big.loose_eq(&Value::Number(Number::Float((1u64 << 53) as f64))));
+    }
+
+    #[test]
+    fn try_into_containers() {
+        let value: Value = json!({"a": [1, 2]}).into();
+        let obj = value.clone().try_into_object().unwrap();
+        assert_eq!(&obj, value.as_object().unwrap());
+        assert_eq!(value.clone().try_into_array(), Err(value));
+
+        let arr = obj.get("a").unwrap().clone().try_into_array().unwrap();
+        assert_eq!(arr.len(), 2);
+        let string = Value::String("a".to_owned());
+        assert_eq!(string.clone().try_into_object(), Err(string));
+    }
+
+    #[test]
+    fn typed_array_extraction() {
+        let ints: Value = json!([1, -2, 3]).into();
+        assert_eq!(ints.as_i64_vec(), Some(vec![1, -2, 3]));
+        assert_eq!(ints.as_f64_vec(), Some(vec![1.0, -2.0, 3.0]));
+        assert_eq!(ints.as_string_vec(), None);
+
+        let strings: Value = json!(["a", "b"]).into();
+        assert_eq!(
+            strings.as_string_vec(),
+            Some(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(strings.as_i64_vec(), None);
+
+        let mixed: Value = json!([1, "two", 3.5]).into();
+        assert_eq!(mixed.as_i64_vec(), None);
+        assert_eq!(mixed.as_f64_vec(), None);
+        assert_eq!(mixed.as_string_vec(), None);
+
+        let floats: Value = json!([1, 2.5]).into();
+        assert_eq!(floats.as_i64_vec(), None);
+        assert_eq!(floats.as_f64_vec(), Some(vec![1.0, 2.5]));
+
+        assert_eq!(Value::Null.as_i64_vec(), None);
+        assert_eq!(Value::new_array(0).as_i64_vec(), Some(vec![]));
+    }
+
+    #[test]
+    fn counts_matching_nodes() {
+        let value: Value = json!({
+            "a": null,
+            "b": [null, 1, {"c": null, "d": []}],
+            "e": {"f": {}, "g": null}
+        })
+        .into();
+        assert_eq!(value.count_matching(Value::is_null), 4);
+        assert_eq!(value.count_matching_leaves(Value::is_null), 4);
+        assert_eq!(value.count_matching(|_| true), 11);
+        assert_eq!(value.count_matching_leaves(|_| true), 7);
+        assert_eq!(value.count_matching(Value::is_object), 4);
+        assert_eq!(value.count_matching_leaves(Value::is_object), 1);
+        assert_eq!(Value::Null.count_matching_leaves(Value::is_null), 1);
+    }
+
+    #[test]
+    fn replaces_matching_nodes() {
+        let mut value: Value = json!({
+            "name": "TBD",
+            "items": [{"price": "TBD", "qty": 2}, "TBD", ["TBD"]],
+            "note": "TBD later"
+        })
+        .into();
+        let tbd = Value::from("TBD");
+        value.replace_all(|node| {
+            if *node == tbd {
+                Some(Value::Null)
+            } else {
+                None
+            }
+        });
+        let expected: Value = json!({
+            "name": null,
+            "items": [{"price": null, "qty": 2}, null, [null]],
+            "note": "TBD later"
+        })
+        .into();
+        assert_eq!(value, expected);
+
+        let mut value: Value = json!({"a": {}, "b": {"c": {}}, "d": []}).into();
+        let empty = Value::new_object();
+        value.replace_all_leaves(|node| {
+            if *node == empty {
+                Some(Value::from(0))
+            } else {
+                None
+            }
+        });
+        assert_eq!(value, json!({"a": 0, "b": {"c": 0}, "d": []}));
+
+        let mut value: Value = json!([[1], [2]]).into();
+        value.replace_all(|node| {
+            if node.is_array() {
+                Some(json!([[0]]).into())
+            } else {
+                None
+            }
+        });
+        assert_eq!(value, json!([[0]]));
+    }
+
+    #[test]
+    fn normalizes_integral_floats() {
+        let mut value: Value = json!({"a": 5.0, "b": [5.5, 1e300, -2.0, 0.0], "c": 7}).into();
+        value.normalize_numbers();
+        assert_eq!(value["a"], Value::Number(Number::PosInt(5)));
+        assert_eq!(value["b"][0], Value::Number(Number::Float(5.5)));
+        assert_eq!(value["b"][1], Value::Number(Number::Float(1e300)));
+        assert_eq!(value["b"][2], Value::Number(Number::NegInt(-2)));
+        assert_eq!(value["b"][3], Value::Number(Number::PosInt(0)));
+        assert_eq!(value["c"], Value::Number(Number::PosInt(7)));
+
+        let mut edges = Value::from_array_iter(
+            [
+                18_446_744_073_709_551_616.0,
+                9_007_199_254_740_992.0,
+                -9_223_372_036_854_775_808.0,
+            ]
+            .iter()
+            .map(|f| Value::Number(Number::Float(*f))),
+        );
+        edges.normalize_numbers();
+        assert_eq!(
+            edges[0],
+            Value::Number(Number::Float(18_446_744_073_709_551_616.0))
+        );
+        assert_eq!(
+            edges[1],
+            Value::Number(Number::PosInt(9_007_199_254_740_992))
+        );
+        assert_eq!(edges[2], Value::Number(Number::NegInt(i64::min_value())));
+    }
+
+    #[test]
+    fn object_keys_and_array_len() {
+        let obj: Value = json!({"b": 1, "a": [1, 2], "c": {}}).into();
+        assert_eq!(obj.object_keys().unwrap(), ["a", "b", "c"]);
+        assert_eq!(obj.array_len(), None);
+        assert_eq!(obj["a"].array_len(), Some(2));
+        assert_eq!(obj["a"].object_keys(), None);
+        assert_eq!(obj["c"].object_keys(), Some(Vec::new()));
+        assert_eq!(Value::new_array(0).array_len(), Some(0));
+        assert_eq!(Value::from(1u64).object_keys(), None);
+        assert_eq!(Value::from(1u64).array_len(), None);
+    }
+
+    #[test]
+    fn sorts_all_arrays() {
+        let mut a: Value = json!({
+            "tags": ["b", "a", 3, null],
+            "pairs": [[3, 1], [2, 2]],
+            "items": [{"x": [2, 1]}, {"x": [1]}, true]
+        })
+        .into();
+        let mut b: Value = json!({
+            "tags": [null, "a", 3, "b"],
+            "pairs": [[2, 2], [1, 3]],
+            "items": [true, {"x": [1]}, {"x": [1, 2]}]
+        })
+        .into();
+        assert_ne!(a, b);
+        a.sort_all_arrays();
+        b.sort_all_arrays();
+        assert_eq!(a, b);
+        assert_eq!(a["tags"], json!([null, 3, "a", "b"]));
+        assert_eq!(a["pairs"], json!([[1, 3], [2, 2]]));
+
+        let mut scalar = Value::from("x");
+        scalar.sort_all_arrays();
+        assert_eq!(scalar, Value::from("x"));
+    }
+
+    #[test]
+    fn sorts_numbers_by_value() {
+        let mut value: Value = json!([-1, 2, 0.5]).into();
+        value.sort_all_arrays();
+        assert_eq!(value, json!([-1, 0.5, 2]));
+
+        let (min, max) = (i64::min_value(), u64::max_value());
+        let mut value: Value = json!([3, -2.5, max, "a", min, 0, null, 1.5, -1]).into();
+        value.sort_all_arrays();
+        assert_eq!(value, json!([null, min, -2.5, -1, 0, 1.5, 3, max, "a"]));
+
+        let five = Value::Number(Number::Float(5.0));
+        assert_eq!(five.total_cmp(&Value::from(5u64)), Ordering::Less);
+        assert_eq!(Value::from(5u64).total_cmp(&five), Ordering::Greater);
+
+        let nan = Value::Number(Number::Float(f64::NAN));
+        let mut value = Value::from_array_iter(vec![
+            Value::from(1u64),
+            nan.clone(),
+            Value::Number(Number::Float(-0.5)),
+            nan,
+            Value::from(-3i64),
+        ]);
+        value.sort_all_arrays();
+        let sorted = value.as_array().unwrap();
+        assert_eq!(sorted.get(0), Some(&Value::from(-3i64)));
+        assert_eq!(sorted.get(1), Some(&Value::Number(Number::Float(-0.5))));
+        assert_eq!(sorted.get(2), Some(&Value::from(1u64)));
+        for idx in 3..5 {
+            match sorted.get(idx) {
+                Some(Value::Number(Number::Float(f))) => assert!(f.is_nan()),
+                other => panic!("expected NaN, found {:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn clamps_numbers() {
+        let mut value: Value = json!({
+            "a": -5,
+            "b": 150,
+            "c": 50,
+            "d": 101.5,
+            "e": -0.5,
+            "f": [200, 3.25, u64::max_value()],
+            "g": "1000"
+        })
+        .into();
+        value.clamp_numbers(0.0, 100.0);
+        assert_eq!(value["a"], Value::Number(Number::PosInt(0)));
+        assert_eq!(value["b"], Value::Number(Number::PosInt(100)));
+        assert_eq!(value["c"], Value::Number(Number::PosInt(50)));
+        assert_eq!(value["d"], Value::Number(Number::Float(100.0)));
+        assert_eq!(value["e"], Value::Number(Number::Float(0.0)));
+        assert_eq!(value["f"], json!([100, 3.25, 100]));
+        assert_eq!(value["g"], Value::from("1000"));
+
+        let mut value: Value = json!([-3, 20, 1]).into();
+        value.clamp_numbers(-1.5, 1e20);
+        assert_eq!(value[0], Value::Number(Number::Float(-1.5)));
+        assert_eq!(value[1], Value::Number(Number::PosInt(20)));
+        value.clamp_numbers(1e19, 1e20);
+        assert_eq!(
+            value[1],
+            Value::Number(Number::PosInt(10_000_000_000_000_000_000))
+        );
+        value.clamp_numbers(3e19, 1e20);
+        assert_eq!(value[2], Value::Number(Number::Float(3e19)));
+    }
+
+    #[test]
+    fn joins_string_arrays() {
+        let value: Value = json!(["a", "b", "c"]).into();
+        assert_eq!(value.join(", "), Some("a, b, c".to_owned()));
+        assert_eq!(value.join(""), Some("abc".to_owned()));
+        let single: Value = json!(["only"]).into();
+        assert_eq!(single.join(", "), Some("only".to_owned()));
+        assert_eq!(Value::new_array(0).join(", "), Some(String::new()));
+
+        let mixed: Value = json!(["a", 1, "c"]).into();
+        assert_eq!(mixed.join(", "), None);
+        assert_eq!(Value::from("a").join(", "), None);
+    }
+
+    #[test]
+    fn subset_matching() {
+        let whole: Value = json!({
+            "id": 7,
+            "user": {"name": "ada", "roles": ["admin", "dev"], "active": true},
+            "extra": null
+        })
+        .into();
+        let part: Value = json!({"user": {"name": "ada", "roles": ["admin", "dev"]}}).into();
+        assert!(part.is_subset_of(&whole));
+        assert!(whole.is_subset_of(&whole));
+        assert!(Value::new_object().is_subset_of(&whole));
+        assert!(!whole.is_subset_of(&part));
+
+        let differs: Value = json!({"user": {"name": "bob"}}).into();
+        assert!(!differs.is_subset_of(&whole));
+        let partial_array: Value = json!({"user": {"roles": ["admin"]}}).into();
+        assert!(!partial_array.is_subset_of(&whole));
+        let missing: Value = json!({"other": 1}).into();
+        assert!(!missing.is_subset_of(&whole));
+        assert!(!Value::new_object().is_subset_of(&Value::Null));
+    }
+
+    #[test]
+    fn number_display_matches_serde_json() {
+        for f in &[1.0, 1e20, 0.1, -2.5, 1e-7, 123456789.125, f64::MAX, 5e-324] {
+            let expected = JsonNumber::from_f64(*f).unwrap().to_string();
+            assert_eq!(Number::Float(*f).to_string(), expected);
+        }
+        assert_eq!(Number::Float(1.0).to_string(), "1.0");
+        assert_eq!(Number::Float(1e20).to_string(), "1e20");
+        assert_eq!(Number::Float(0.1).to_string(), "0.1");
+        assert_eq!(Number::PosInt(7).to_string(), "7");
+        assert_eq!(Number::NegInt(-7).to_string(), "-7");
+    }
+
+    #[test]
+    fn number_accessors() {
+        let mut value = Value::Number(Number::NegInt(-4));
+        assert_eq!(value.as_number(), Some(&Number::NegInt(-4)));
+        *value.as_number_mut().unwrap() = Number::Float(0.5);
+        assert_eq!(value, Value::Number(Number::Float(0.5)));
+
+        let mut string = Value::String("4".to_owned());
+        assert_eq!(string.as_number(), None);
+        assert_eq!(string.as_number_mut(), None);
+    }
+
+    #[test]
+    fn compares_with_serde_json() {
+        let value: Value = json!({"a": [1, 2.5, "x"], "b": {"c": null, "d": true}}).into();
+        assert_eq!(
+            value,
+            json!({"b": {"d": true, "c": null}, "a": [1.0, 2.5, "x"]})
+        );
+        assert_eq!(
+            json!({"a": [1, 2.5, "x"], "b": {"c": null, "d": true}}),
+            value
+        );
+        assert_ne!(
+            value,
+            json!({"a": [1, 2.5, "y"], "b": {"c": null, "d": true}})
+        );
+        assert_ne!(value, json!({"a": [1, 2.5, "x"], "b": {"c": null}}));
+        assert_ne!(
+            value,
+            json!({"a": [1, 2.5, "x"], "b": {"c": null, "d": true}, "e": 1})
+        );
+        assert_ne!(Value::Number(5.into()), json!("5"));
+    }
+
+    #[test]
+    fn truthiness() {
+        let falsy: Value = json!([null, false, 0, 0.0, -0.0, "", [], {}]).into();
+        for value in falsy.as_array().unwrap().iter() {
+            assert!(!value.is_truthy(), "{:?}", value);
+        }
+        let truthy: Value = json!([true, 1, -1, 0.5, "0", "false", [0], {"a": null}]).into();
+        for value in truthy.as_array().unwrap().iter() {
+            assert!(value.is_truthy(), "{:?}", value);
+        }
+    }
+
+    #[test]
+    fn container_constructors() {
+        assert_eq!(Value::new_array(16), json!([]));
+        assert_eq!(Value::new_object(), json!({}));
+        assert_eq!(
+            Value::array_of((1..4u64).map(Value::from)),
+            json!([1, 2, 3])
+        );
+    }
+
+    #[test]
+    fn array_from_iter_and_extend() {
+        let mut value = Value::from_array_iter((0..3u64).map(|n| Value::Number(n.into())));
+        let expected: Value = json!([0, 1, 2]).into();
+        assert_eq!(value, expected);
+
+        assert!(value.array_extend((3..5u64).map(|n| Value::Number(n.into()))));
+        let expected: Value = json!([0, 1, 2, 3, 4]).into();
+        assert_eq!(value, expected);
+
+        let collected: Value = vec![Value::Null, Value::Bool(true)].into_iter().collect();
+        let expected: Value = json!([null, true]).into();
+        assert_eq!(collected, expected);
+
+        let mut value = Value::Null;
+        assert!(!value.array_extend(vec![Value::Null]));
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn object_keys_vector_is_sorted() {
+        let value: Value = json!({"d": 1, "b": 2, "a": 3, "c": 4}).into();
+        let obj = value.as_object().unwrap();
+        let keys: Vec<&String> = obj.keys_vector().iter().collect();
+        assert_eq!(keys, vec!["a", "b", "c", "d"]);
+        assert!(keys.into_iter().eq(obj.iter().map(|(k, _)| k)));
+    }
+
+    #[test]
+    fn coerce_number_from_strings() {
+        let mut value = Value::String("42".to_owned());
+        assert!(value.coerce_number());
+        assert_eq!(value, Value::Number(Number::PosInt(42)));
+
+        let mut value = Value::String("-3".to_owned());
+        assert!(value.coerce_number());
+        assert_eq!(value, Value::Number(Number::NegInt(-3)));
+
+        let mut value = Value::String("1.5".to_owned());
+        assert!(value.coerce_number());
+        assert_eq!(value, Value::Number(Number::Float(1.5)));
+
+        for s in &["abc", "1e400", "NaN", ""] {
+            let mut value = Value::String(s.to_string());
+            assert!(!value.coerce_number());
+            assert_eq!(value, Value::String(s.to_string()));
+        }
+
+        let mut value = Value::Bool(true);
+        assert!(!value.coerce_number());
+    }
+
+    #[test]
+    fn deep_clone_detached_breaks_sharing() {
+        let value: Value = json!({"a": [1, 2, 3], "b": {"c": "d"}}).into();
+        let shared = value.clone();
+        let detached = value.deep_clone_detached();
+        assert!(value.ptr_eq(&shared));
+        assert!(value["a"].ptr_eq(&shared["a"]));
+        assert!(!value.ptr_eq(&detached));
+        assert!(!value["a"].ptr_eq(&detached["a"]));
+        assert!(!value["b"].ptr_eq(&detached["b"]));
+        assert_eq!(value, detached);
+    }
+
+    #[test]
+    fn ptr_eq_sees_edits_anywhere() {
+        let original = Value::from_array_iter((0..1000u64).map(Value::from));
+        let mut edited = original.clone();
+        assert!(edited.ptr_eq(&original));
+        edited[500] = Value::Null;
+        assert!(!edited.ptr_eq(&original));
+
+        let entries = (0..1000u64)
+            .map(|n| (n.to_string(), Value::from(n)))
+            .collect();
+        let obj = Value::Object(Object::from_entries(entries));
+        let mut edited = obj.clone();
+        assert!(edited.ptr_eq(&obj));
+        edited["500"] = Value::Null;
+        assert!(!edited.ptr_eq(&obj));
+
+        let (empty, other_empty) = (Value::new_array(0), Value::new_array(0));
+        assert!(empty.ptr_eq(&empty));
+        assert!(!empty.ptr_eq(&other_empty));
+        assert!(!Value::new_object().ptr_eq(&Value::new_object()));
+    }
+
+    #[test]
+    fn editing_clone_leaves_snapshot_unchanged() {
+        let snapshot: Value = json!({"a": [1, 2, 3], "b": {"c": "d"}, "e": [4]}).into();
+        let mut copy = snapshot.clone();
+        assert!(copy.ptr_eq(&snapshot));
+
+        copy["a"][1] = Value::from("two");
+        copy["b"]["c"] = Value::Null;
+
+        assert_eq!(snapshot, json!({"a": [1, 2, 3], "b": {"c": "d"}, "e": [4]}));
+        assert_eq!(
+            copy,
+            json!({"a": [1, "two", 3], "b": {"c": null}, "e": [4]})
+        );
+        assert!(!copy["a"].ptr_eq(&snapshot["a"]));
+        assert!(copy["e"].ptr_eq(&snapshot["e"]));
+    }
+
+    #[test]
+    fn object_rename_keys() {
+        let mut value: Value = json!({"b": 1, "a": 2, "c": 3, "_d": 4}).into();
+        let obj = value.as_object_mut().unwrap();
+        obj.rename_keys(|key| Some(key.to_uppercase()));
+        let keys: Vec<&String> = obj.keys().collect();
+        assert_eq!(keys, vec!["A", "B", "C", "_D"]);
+        assert_eq!(obj.get("A"), Some(&Value::Number(Number::PosInt(2))));
+        assert_eq!(obj.get("_D"), Some(&Value::Number(Number::PosInt(4))));
+
+        obj.rename_keys(|key| {
+            if key == "C" {
+                Some("A".to_owned())
+            } else {
+                None
+            }
+        });
+        let expected: Value = json!({"A": 3, "B": 1, "_D": 4}).into();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn converts_back_to_serde_json() {
+        let json = json!({
+            "a": [1, -2, 3.5, u64::max_value(), i64::min_value()],
+            "b": {"c": null, "d": "e", "f": false}
+        });
+        let value: Value = json.clone().into();
+        assert_eq!(JsonValue::from(&value), json);
+        assert_eq!(JsonValue::from(value), json);
+    }
+
+    #[test]
+    fn from_str_borrowed_parses_strings() {
+        let input = r#"{"greeting": "hello", "names": ["ann", "bob", "caf\u00e9"], "": ""}"#;
+        let value = Value::from_str_borrowed(input).unwrap();
+        assert_eq!(value["greeting"].as_str(), Some("hello"));
+        assert_eq!(value["names"][2].as_str(), Some("café"));
+        assert_eq!(value[""].as_str(), Some(""));
+        assert_eq!(value["names"].as_array().unwrap().len(), 3);
+        assert!(matches!(
+            Value::from_str_borrowed("{\"a\": }"),
+            Err(ParseError::Syntax(_))
+        ));
+    }
+
+    #[test]
+    fn object_compares_as_map() {
+        let mut first = Object::new();
+        first.insert("a".to_owned(), Value::Number(1.into()));
+        first.insert("b".to_owned(), Value::Number(2.into()));
+        let mut second = Object::new();
+        second.insert("b".to_owned(), Value::Number(2.into()));
+        second.insert("a".to_owned(), Value::Number(1.into()));
+        assert_eq!(first, second);
+        assert_eq!(first.partial_cmp(&second), Some(Ordering::Equal));
+
+        let larger: Value = json!({"a": 1, "b": 3}).into();
+        let larger = larger.as_object().unwrap();
+        assert!(first < *larger);
+        assert!(*larger > first);
+
+        let shorter: Value = json!({"a": 2}).into();
+        assert!(first < *shorter.as_object().unwrap());
+        let prefix: Value = json!({"a": 1}).into();
+        assert!(*prefix.as_object().unwrap() < first);
+    }
+
+    #[test]
+    fn prune_cascades() {
+        let original: Value = json!({
+            "a": null,
+            "b": {"c": null, "d": []},
+            "e": [{"f": null}, null],
+            "g": 1
+        })
+        .into();
+
+        let mut value = original.clone();
+        value.prune(PruneOptions {
+            nulls: true,
+            ..PruneOptions::default()
+        });
+        let expected: Value = json!({"b": {"d": []}, "e": [{}, null], "g": 1}).into();
+        assert_eq!(value, expected);
+
+        let mut value = original.clone();
+        value.prune(PruneOptions {
+            nulls: true,
+            empty_arrays: true,
+            empty_objects: true,
+        });
+        let expected: Value = json!({"e": [{}, null], "g": 1}).into();
+        assert_eq!(value, expected);
+
+        let mut value = original.clone();
+        value.prune(PruneOptions::default());
+        assert_eq!(value, original);
+    }
+
+    #[test]
+    fn metrics_of_document() {
+        let value: Value = json!({"a": [1, 2, {"b": null}], "c": "d", "e": {}}).into();
+        assert_eq!(
+            value.metrics(),
+            DocMetrics {
+                nodes: 8,
+                max_depth: 3,
+                object_keys: 4,
+                array_elements: 3,
+            }
+        );
+        assert_eq!(
+            Value::Null.metrics(),
+            DocMetrics {
+                nodes: 1,
+                ..DocMetrics::default()
+            }
+        );
+    }
+
+    #[test]
+    fn object_invariants_detect_and_repair() {
+        let value: Value = json!({"a": 1, "b": 2, "c": 3}).into();
+        let mut obj = value.as_object().unwrap().clone();
+        assert_eq!(obj.validate_invariants(), Ok(()));
+
+        obj.keys.push_back("b".to_owned());
+        obj.values.push_back(Value::Number(4.into()));
+        assert_eq!(
+            obj.validate_invariants(),
+            Err(InvariantError::Unsorted { index: 3 })
+        );
+        obj.keys.insert(2, "b".to_owned());
+        obj.values.insert(2, Value::Number(5.into()));
+        assert_eq!(
+            obj.validate_invariants(),
+            Err(InvariantError::Duplicate { index: 2 })
+        );
+
+        obj.repair();
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        let expected: Value = json!({"a": 1, "b": 4, "c": 3}).into();
+        assert_eq!(Value::Object(obj.clone()), expected);
+
+        obj.keys.push_back("d".to_owned());
+        assert_eq!(
+            obj.validate_invariants(),
+            Err(InvariantError::LengthMismatch { keys: 4, values: 3 })
+        );
+        obj.repair();
+        assert_eq!(Value::Object(obj), expected);
+    }
+
+    #[test]
+    fn object_as_parallel_is_aligned() {
+        let value: Value = json!({"b": [1], "a": null, "c": "x"}).into();
+        let obj = value.as_object().unwrap();
+        let (keys, values) = obj.as_parallel();
+        assert_eq!(keys.len(), values.len());
+        assert!(keys.iter().zip(values.iter()).eq(obj.iter()));
+    }
+
+    #[test]
+    fn object_key_sets() {
+        let a: Value = json!({"a": 1, "b": 2, "c": 3}).into();
+        let b: Value = json!({"b": null, "c": [], "d": 4}).into();
+        let c: Value = json!({"x": 1}).into();
+        let same: Value = json!({"a": true, "b": false, "c": null}).into();
+        let (a, b, c, same) = (
+            a.as_object().unwrap(),
+            b.as_object().unwrap(),
+            c.as_object().unwrap(),
+            same.as_object().unwrap(),
+        );
+
+        assert!(a.keys_equal(same));
+        assert!(!a.keys_equal(b));
+        let (only_a, only_same) = a.key_diff(same);
+        assert!(only_a.is_empty() && only_same.is_empty());
+        let (only_a, only_b) = a.key_diff(b);
+        assert_eq!(only_a, vec!["a"]);
+        assert_eq!(only_b, vec!["d"]);
+        let (only_a, only_c) = a.key_diff(c);
+        assert_eq!(only_a, vec!["a", "b", "c"]);
+        assert_eq!(only_c, vec!["x"]);
+    }
+
+    #[test]
+    fn object_builder_matches_inserts() {
+        let built = Object::builder()
+            .set("b", "x")
+            .set("a", 1)
+            .set(String::from("c"), Object::builder().set("d", true).build())
+            .set("a", 2.5)
+            .build();
+
+        let mut inner = Object::new();
+        inner.insert("d".to_owned(), Value::Bool(true));
+        let mut inserted = Object::new();
+        inserted.insert("a".to_owned(), Value::Number(Number::Float(2.5)));
+        inserted.insert("b".to_owned(), Value::String("x".to_owned()));
+        inserted.insert("c".to_owned(), Value::Object(inner));
+
+        assert_eq!(built, inserted);
+        assert_eq!(built.validate_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn object_from_iter_with_policy() {
+        let entries = || vec![("b", 1), ("a", 2), ("b", 3), ("c", 4), ("b", 5)];
+
+        let obj = Object::from_iter_with_policy(entries(), DupPolicy::LastWins).unwrap();
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        assert_eq!(Value::Object(obj), json!({"a": 2, "b": 5, "c": 4}));
+
+        let obj = Object::from_iter_with_policy(entries(), DupPolicy::FirstWins).unwrap();
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        assert_eq!(Value::Object(obj), json!({"a": 2, "b": 1, "c": 4}));
+
+        let err = Object::from_iter_with_policy(entries(), DupPolicy::Error).unwrap_err();
+        assert_eq!(
+            err,
+            DupError {
+                key: "b".to_owned()
+            }
+        );
+        assert_eq!(err.to_string(), "duplicate object key \"b\"");
+
+        let unique = vec![("x", true), ("y", false)];
+        let obj = Object::from_iter_with_policy(unique, DupPolicy::Error).unwrap();
+        assert_eq!(Value::Object(obj), json!({"x": true, "y": false}));
+    }
+
+    #[test]
+    fn object_rekey_with_collisions() {
+        let original: Value = json!({"a": 1, "b": 2, "c": 3, "z": 0}).into();
+        let original = original.as_object().unwrap().clone();
+        let mapping_of = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect()
+        };
+        let mapping = mapping_of(&[("a", "x"), ("b", "c"), ("q", "r")]);
+
+        let mut obj = original.clone();
+        obj.rekey(&mapping, DupPolicy::LastWins).unwrap();
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        assert_eq!(Value::Object(obj), json!({"c": 3, "x": 1, "z": 0}));
+
+        let mut obj = original.clone();
+        obj.rekey(&mapping, DupPolicy::FirstWins).unwrap();
+        assert_eq!(Value::Object(obj), json!({"c": 2, "x": 1, "z": 0}));
+
+        let mut obj = original.clone();
+        let err = obj.rekey(&mapping, DupPolicy::Error).unwrap_err();
+        assert_eq!(err.key, "c");
+        assert_eq!(obj, original);
+
+        let mut obj = original.clone();
+        obj.rekey(&mapping_of(&[("a", "b"), ("b", "a")]), DupPolicy::Error)
+            .unwrap();
+        assert_eq!(Value::Object(obj), json!({"a": 2, "b": 1, "c": 3, "z": 0}));
+
+        let mut obj = Object::with_insertion_order();
+        for key in &["z", "a", "m"] {
+            obj.insert(key.to_string(), Value::Null);
+        }
+        obj.rekey(&mapping_of(&[("a", "b")]), DupPolicy::Error)
+            .unwrap();
+        obj.insert("c".to_owned(), Value::Null);
+        assert!(obj.tracks_insertion_order());
+        let keys: Vec<&String> = obj.iter_insertion_order().map(|(key, _)| key).collect();
+        assert_eq!(keys, ["z", "b", "m", "c"]);
+    }
+
+    #[test]
+    fn object_trim_keys() {
+        let value: Value = json!({" a": 1, "a ": 2, "\tb": 3, "c": 4, " ": 5}).into();
+        let mut obj = value.as_object().unwrap().clone();
+        obj.trim_keys();
+        let expected: Value = json!({"": 5, "a": 2, "b": 3, "c": 4}).into();
+        assert_eq!(Value::Object(obj.clone()), expected);
+        assert_eq!(obj.validate_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn object_iter_size_hint() {
+        let value: Value = json!({"a": 1, "b": 2, "c": 3}).into();
+        let mut obj = value.as_object().unwrap().clone();
+
+        let mut iter = obj.iter();
+        assert_eq!(iter.size_hint(), (3, Some(3)));
+        iter.next();
+        assert_eq!(iter.len(), 2);
+        iter.next_back();
+        assert_eq!(iter.size_hint(), (1, Some(1)));
+        iter.next();
+        assert_eq!(iter.len(), 0);
+
+        let mut iter = obj.iter_mut();
+        assert_eq!(iter.len(), 3);
+        iter.next();
+        assert_eq!(iter.size_hint(), (2, Some(2)));
+    }
+
+    #[test]
+    fn object_remove_unordered_then_repair() {
+        let value: Value = json!({"a": 1, "b": 2, "c": 3, "d": 4, "e": 5}).into();
+        let mut obj = value.as_object().unwrap().clone();
+
+        assert_eq!(obj.remove_unordered("b"), Some(Value::Number(2.into())));
+        assert!(obj.validate_invariants().is_err());
+        assert_eq!(obj.remove_unordered("e"), Some(Value::Number(5.into())));
+        assert_eq!(obj.remove_unordered("a"), Some(Value::Number(1.into())));
+        assert_eq!(obj.remove_unordered("z"), None);
+
+        obj.repair();
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        let expected: Value = json!({"c": 3, "d": 4}).into();
+        assert_eq!(Value::Object(obj.clone()), expected);
+        assert_eq!(obj.get("d"), Some(&Value::Number(4.into())));
+    }
+
+    #[test]
+    fn object_get_or_insert_with() {
+        let mut obj = Object::new();
+        obj.insert("a".to_owned(), Value::Number(1.into()));
+
+        let present = obj.get_or_insert_with("a", || panic!("a is present"));
+        assert_eq!(*present, Value::Number(1.into()));
+        *present = Value::Bool(true);
+
+        let absent = obj.get_or_insert_with("b", || Value::Number(2.into()));
+        assert_eq!(*absent, Value::Number(2.into()));
+        *absent = Value::Null;
+
+        let expected: Value = json!({"a": true, "b": null}).into();
+        assert_eq!(Value::Object(obj), expected);
+    }
+
+    #[test]
+    fn object_append_replaces_duplicate_keys() {
+        let first: Value = json!({"a": 1, "b": 2}).into();
+        let second: Value = json!({"b": 3, "c": 4}).into();
+        let mut obj = first.as_object().unwrap().clone();
+        let mut other = second.as_object().unwrap().clone();
+        obj.append(&mut other);
+        assert!(other.is_empty());
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        let expected: Value = json!({"a": 1, "b": 3, "c": 4}).into();
+        assert_eq!(Value::Object(obj), expected);
+    }
+
+    #[test]
+    fn object_add_right_wins() {
+        let left: Value = json!({"a": 1, "b": {"x": 1}, "d": 4}).into();
+        let right: Value = json!({"b": {"y": 2}, "c": 3}).into();
+        let left = left.as_object().unwrap().clone();
+        let right = right.as_object().unwrap().clone();
+
+        let merged = left.clone() + right.clone();
+        assert_eq!(merged.validate_invariants(), Ok(()));
+        let expected: Value = json!({"a": 1, "b": {"y": 2}, "c": 3, "d": 4}).into();
+        assert_eq!(Value::Object(merged.clone()), expected);
+        assert_eq!(&left + &right, merged);
+
+        let unchanged_left: Value = json!({"a": 1, "b": {"x": 1}, "d": 4}).into();
+        let unchanged_right: Value = json!({"b": {"y": 2}, "c": 3}).into();
+        assert_eq!(Value::Object(left.clone()), unchanged_left);
+        assert_eq!(Value::Object(right), unchanged_right);
+        assert_eq!(&left + &Object::new(), left);
+    }
+
+    #[test]
+    fn object_merge_with_sums_shared_keys() {
+        let left: Value = json!({"a": 1, "b": 2, "d": "x"}).into();
+        let right: Value = json!({"b": 10, "c": 20, "d": "y"}).into();
+        let mut obj = left.as_object().unwrap().clone();
+        let mut seen = Vec::new();
+        obj.merge_with(right.as_object().unwrap().clone(), |key, a, b| {
+            seen.push(key.to_owned());
+            match (
+                a.as_number().and_then(Number::as_i64),
+                b.as_number().and_then(Number::as_i64),
+            ) {
+                (Some(a), Some(b)) => Value::from(a + b),
+                _ => b,
+            }
+        });
+        assert_eq!(seen, ["b", "d"]);
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        let expected: Value = json!({"a": 1, "b": 12, "c": 20, "d": "y"}).into();
+        assert_eq!(Value::Object(obj), expected);
+    }
+
+    #[test]
+    fn object_merge_recursive_caps_depth() {
+        let left: Value = json!({"a": {"x": 1, "y": {"z": 2}}, "b": [1]}).into();
+        let right: Value = json!({"a": {"y": {"w": 3}, "x": null}, "b": {"c": 4}}).into();
+        let mut obj = left.as_object().unwrap().clone();
+        obj.merge_recursive(right.as_object().unwrap().clone(), 3)
+            .unwrap();
+        let expected: Value =
+            json!({"a": {"x": null, "y": {"w": 3, "z": 2}}, "b": {"c": 4}}).into();
+        assert_eq!(Value::Object(obj.clone()), expected);
+        assert_eq!(
+            obj.merge_recursive(right.as_object().unwrap().clone(), 2),
+            Err(DepthError { max_depth: 2 })
+        );
+
+        let (mut deep_left, mut deep_right) = (Value::Null, Value::Null);
+        for idx in 0..1000 {
+            let wrap = |inner: Value| {
+                let mut obj = Object::new();
+                obj.insert("k".to_owned(), inner);
+                obj.insert(format!("v{}", idx), Value::from(idx as u64));
+                Value::Object(obj)
+            };
+            deep_left = wrap(deep_left);
+            deep_right = wrap(deep_right);
+        }
+        let mut obj = deep_left.as_object().unwrap().clone();
+        let err = obj
+            .merge_recursive(deep_right.as_object().unwrap().clone(), 10)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "merge exceeds the maximum depth of 10");
+        assert_eq!(Value::Object(obj), deep_left);
+    }
+
+    #[test]
+    fn object_append_sorted_unchecked() {
+        let first: Value = json!({"2024-01": 1, "2024-02": 2}).into();
+        let second: Value = json!({"2024-03": 3, "2024-04": 4}).into();
+        let mut obj = first.as_object().unwrap().clone();
+        obj.append_sorted_unchecked(second.as_object().unwrap().clone());
+        obj.append_sorted_unchecked(Object::new());
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        let expected: Value =
+            json!({"2024-01": 1, "2024-02": 2, "2024-03": 3, "2024-04": 4}).into();
+        assert_eq!(Value::Object(obj.clone()), expected);
+        assert_eq!(obj.get("2024-03"), Some(&Value::Number(3.into())));
+
+        let mut empty = Object::new();
+        empty.append_sorted_unchecked(obj.clone());
+        assert_eq!(empty, obj);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "overlapping keys")]
+    fn object_append_sorted_unchecked_checks_boundary() {
+        let first: Value = json!({"a": 1, "c": 3}).into();
+        let second: Value = json!({"b": 2}).into();
+        let mut obj = first.as_object().unwrap().clone();
+        obj.append_sorted_unchecked(second.as_object().unwrap().clone());
+    }
+
+    #[test]
+    fn object_position_of() {
+        let value: Value = json!({"d": 4, "b": 2, "a": 1, "c": 3}).into();
+        let obj = value.as_object().unwrap();
+        for (idx, (key, value)) in obj.iter().enumerate() {
+            assert_eq!(obj.position_of(key.as_str()), Some(idx));
+            assert_eq!(obj.as_parallel().1.get(idx), Some(value));
+        }
+        assert_eq!(obj.position_of("e"), None);
+    }
+
+    #[test]
+    fn object_iter_indexed() {
+        let value: Value = json!({"c": 3, "a": 1, "b": 2}).into();
+        let obj = value.as_object().unwrap();
+        let indexed: Vec<(usize, &str)> = obj
+            .iter_indexed()
+            .map(|(idx, key, _)| (idx, key.as_str()))
+            .collect();
+        assert_eq!(indexed, vec![(0, "a"), (1, "b"), (2, "c")]);
+        for (idx, key, _) in obj.iter_indexed() {
+            assert_eq!(obj.position_of(key.as_str()), Some(idx));
+        }
+        assert_eq!(Object::new().iter_indexed().count(), 0);
+    }
+
+    #[test]
+    fn object_iter_sorted_by_numeric_keys() {
+        let value: Value = json!({"10": "ten", "9": "nine", "100": "hundred", "x": "other"}).into();
+        let obj = value.as_object().unwrap();
+        let numeric = |key: &str| key.parse::<u64>().unwrap_or(u64::max_value());
+        let keys: Vec<&str> = obj
+            .iter_sorted_by(|a, b| numeric(a).cmp(&numeric(b)))
+            .into_iter()
+            .map(|(key, _)| key.as_str())
+            .collect();
+        assert_eq!(keys, vec!["9", "10", "100", "x"]);
+        let lexicographic = obj.iter_sorted_by(str::cmp);
+        assert_eq!(lexicographic, obj.iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn object_binary_search_by_prefix() {
+        let value: Value =
+            json!({"apple": 1, "banana": 2, "bar": 3, "baz": 4, "cat": 5, "bz": 6}).into();
+        let obj = value.as_object().unwrap();
+        // Treating keys equal to the prefix as greater finds the first key not before it.
+        let prefix_start = |prefix: &str| {
+            obj.binary_search_by(|key| key.cmp(prefix).then(Ordering::Greater))
+                .unwrap_err()
+        };
+        assert_eq!(prefix_start("ba"), 1);
+        assert_eq!(prefix_start("bb"), 4);
+        assert_eq!(prefix_start("a"), 0);
+        assert_eq!(prefix_start("d"), obj.len());
+
+        let with_prefix: Vec<&String> = obj
+            .keys()
+            .skip(prefix_start("ba"))
+            .take_while(|key| key.starts_with("ba"))
+            .collect();
+        assert_eq!(with_prefix, vec!["banana", "bar", "baz"]);
+
+        assert_eq!(obj.binary_search_by(|key| key.cmp("baz")), Ok(3));
+        assert_eq!(obj.binary_search_by(|key| key.cmp("bat")), Err(3));
+        assert_eq!(Object::new().binary_search_by(|_| Ordering::Equal), Err(0));
+    }
+
+    #[test]
+    fn object_reverse_lookup() {
+        let value: Value = json!({"c": [1], "a": "x", "b": [1], "d": null}).into();
+        let obj = value.as_object().unwrap();
+        let list: Value = json!([1]).into();
+        assert!(obj.contains_value(&list));
+        assert_eq!(obj.find_key_by_value(&list).map(String::as_str), Some("b"));
+        assert_eq!(
+            obj.find_key_by_value(&Value::Null).map(String::as_str),
+            Some("d")
+        );
+
+        let absent = Value::from("y");
+        assert!(!obj.contains_value(&absent));
+        assert_eq!(obj.find_key_by_value(&absent), None);
+        assert_eq!(Object::new().find_key_by_value(&Value::Null), None);
+    }
+
+    #[test]
+    fn object_first_and_last() {
+        let value: Value = json!({"m": 1, "a": 2, "z": 3}).into();
+        let obj = value.as_object().unwrap();
+        let (key, value) = obj.first().unwrap();
+        assert_eq!((key.as_str(), value), ("a", &Value::Number(2.into())));
+        let (key, value) = obj.last().unwrap();
+        assert_eq!((key.as_str(), value), ("z", &Value::Number(3.into())));
+
+        let empty = Object::new();
+        assert_eq!(empty.first(), None);
+        assert_eq!(empty.last(), None);
+    }
+
+    #[test]
+    fn object_into_keys_and_values() {
+        let value: Value = json!({"c": 3, "a": 1, "b": [2]}).into();
+        let obj = value.as_object().unwrap().clone();
+        let keys: Vec<String> = obj.clone().into_keys().collect();
+        assert_eq!(keys, vec!["a", "b", "c"]);
+        let values: Value = obj.into_values().collect();
+        let expected: Value = json!([1, [2], 3]).into();
+        assert_eq!(values, expected);
+        assert_eq!(Object::new().into_keys().count(), 0);
+    }
+
+    #[test]
+    fn object_map_values() {
+        let value: Value = json!({"a": 1, "b": 2.5, "c": "x"}).into();
+        let obj = value.as_object().unwrap().clone();
+
+        let doubled = obj.clone().map_values(|value| {
+            Ok::<_, ()>(match value {
+                Value::Number(n) => {
+                    Value::Number(Number::from_f64(n.as_f64().unwrap() * 2.0).unwrap())
+                }
+                other => other,
+            })
+        });
+        let expected: Value = json!({"a": 2.0, "b": 5.0, "c": "x"}).into();
+        assert_eq!(Value::Object(doubled.unwrap()), expected);
+
+        let mut seen = 0;
+        let failed = obj.map_values(|value| {
+            seen += 1;
+            if value.is_string() {
+                Err("unexpected string")
+            } else {
+                Ok(value)
+            }
+        });
+        assert_eq!(failed, Err("unexpected string"));
+        assert_eq!(seen, 3);
+    }
+
+    #[test]
+    fn object_key_intersection_and_difference() {
+        let base: Value = json!({"a": 1, "b": 2, "c": 3, "e": 5}).into();
+        let overlay: Value = json!({"b": null, "d": 4, "e": []}).into();
+        let (base, overlay) = (base.as_object().unwrap(), overlay.as_object().unwrap());
+
+        let shared = base.intersect_keys(overlay);
+        assert_eq!(Value::Object(shared.clone()), json!({"b": 2, "e": 5}));
+        assert_eq!(shared.validate_invariants(), Ok(()));
+        let only_base = base.difference_keys(overlay);
+        assert_eq!(Value::Object(only_base.clone()), json!({"a": 1, "c": 3}));
+        assert_eq!(only_base.validate_invariants(), Ok(()));
+
+        assert!(base.intersect_keys(&Object::new()).is_empty());
+        assert_eq!(&base.difference_keys(&Object::new()), base);
+    }
+
+    #[test]
+    fn object_clone_subset_shares_values() {
+        let value: Value = json!({"a": [1, 2], "b": {"c": 3}, "d": 4, "e": 5}).into();
+        let obj = value.as_object().unwrap();
+
+        let subset = obj.clone_subset(&["e", "b", "missing", "a", "b"]);
+        assert_eq!(subset.validate_invariants(), Ok(()));
+        assert_eq!(
+            Value::Object(subset.clone()),
+            json!({"a": [1, 2], "b": {"c": 3}, "e": 5})
+        );
+        assert!(subset.get("a").unwrap().ptr_eq(obj.get("a").unwrap()));
+        assert!(subset.get("b").unwrap().ptr_eq(obj.get("b").unwrap()));
+        assert!(obj.clone_subset(&[]).is_empty());
+    }
+
+    #[test]
+    fn map_scalars_uppercases_strings() {
+        let mut value: Value = json!({
+            "a": "one",
+            "b": ["two", 3, {"c": "four", "d": null}],
+            "e": {"f": "five"}
+        })
+        .into();
+        let mut seen = Vec::new();
+        value.map_scalars(|scalar| {
+            seen.push(scalar.clone());
+            if let Value::String(s) = scalar {
+                *s = s.to_uppercase();
+            }
+        });
+        let expected: Value = json!({
+            "a": "ONE",
+            "b": ["TWO", 3, {"c": "FOUR", "d": null}],
+            "e": {"f": "FIVE"}
+        })
+        .into();
+        assert_eq!(value, expected);
+        let expected: Value = json!(["one", "two", 3, "four", null, "five"]).into();
+        assert_eq!(Value::from_array_iter(seen), expected);
+    }
+
+    #[test]
+    fn object_get_disjoint_mut() {
+        let mut value: Value = json!({"a": 1, "b": 2, "c": 3}).into();
+        let obj = value.as_object_mut().unwrap();
+        let [a, c, z] = obj.get_disjoint_mut(["a", "c", "z"]);
+        assert!(z.is_none());
+        let (a, c) = (a.unwrap(), c.unwrap());
+        mem::swap(a, c);
+        *a = Value::Null;
+        let expected: Value = json!({"a": null, "b": 2, "c": 1}).into();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn object_get_disjoint_mut_leaves_snapshot_unchanged() {
+        let mut obj = Object::new();
+        for i in 0..1000u64 {
+            obj.insert(format!("k{:04}", i), Value::Number(i.into()));
+        }
+        let snapshot = obj.clone();
+        let [a, b, c] = obj.get_disjoint_mut(["k0990", "k0010", "k0500"]);
+        *a.unwrap() = Value::Null;
+        *b.unwrap() = Value::Null;
+        *c.unwrap() = Value::Null;
+
+        for i in 0..1000u64 {
+            let key = format!("k{:04}", i);
+            assert_eq!(snapshot.get(&key), Some(&Value::Number(i.into())));
+            let expected = match i {
+                10 | 500 | 990 => Value::Null,
+                _ => Value::Number(i.into()),
+            };
+            assert_eq!(obj.get(&key), Some(&expected));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn object_get_disjoint_mut_rejects_duplicates() {
+        let mut value: Value = json!({"a": 1, "b": 2}).into();
+        value
+            .as_object_mut()
+            .unwrap()
+            .get_disjoint_mut(["b", "a", "b"]);
+    }
+
+    #[test]
+    fn object_array_and_object_entries() {
+        let mut grouped = Object::new();
+        for (group, item) in &[
+            ("odd", 1u64),
+            ("even", 2),
+            ("odd", 3),
+            ("even", 4),
+            ("odd", 5),
+        ] {
+            grouped
+                .array_entry(group)
+                .push_back(Value::Number((*item).into()));
+        }
+        grouped
+            .object_entry("meta")
+            .insert("count".to_owned(), Value::Number(5.into()));
+        grouped
+            .object_entry("meta")
+            .insert("done".to_owned(), Value::Bool(true));
+        let expected: Value = json!({
+            "even": [2, 4],
+            "odd": [1, 3, 5],
+            "meta": {"count": 5, "done": true}
+        })
+        .into();
+        assert_eq!(Value::Object(grouped.clone()), expected);
+
+        grouped.array_entry("meta").push_back(Value::Null);
+        grouped.object_entry("odd");
+        let expected: Value = json!({"even": [2, 4], "odd": {}, "meta": [null]}).into();
+        assert_eq!(Value::Object(grouped), expected);
+    }
+
+    /// Arbitrary JSON trees, with extra weight on the integer boundaries. The depth is capped since
+    /// the conversions recurse.
+    fn arb_json() -> impl Strategy<Value = JsonValue> {
+        let boundaries = prop_oneof![
+            Just(JsonValue::from(u64::max_value())),
+            Just(JsonValue::from(i64::max_value())),
+            Just(JsonValue::from(i64::max_value() as u64 + 1)),
+            Just(JsonValue::from(i64::min_value())),
+            Just(JsonValue::from(-1)),
+            Just(JsonValue::from(0)),
+        ];
+        let leaf = prop_oneof![
+            Just(JsonValue::Null),
+            any::<bool>().prop_map(JsonValue::Bool),
+            any::<u64>().prop_map(JsonValue::from),
+            any::<i64>().prop_map(JsonValue::from),
+            boundaries,
+            any::<f64>().prop_filter_map("not finite", |f| JsonNumber::from_f64(f)
+                .map(JsonValue::Number)),
+            ".*".prop_map(JsonValue::String),
+        ];
+        leaf.prop_recursive(16, 256, 8, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..8).prop_map(JsonValue::Array),
+                prop::collection::btree_map(".*", inner, 0..8)
+                    .prop_map(|map| JsonValue::Object(map.into_iter().collect())),
+            ]
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn json_round_trip(json in arb_json()) {
+            let value = Value::from(json.clone());
+            prop_assert_eq!(JsonValue::from(&value), json);
+        }
+    }
 }
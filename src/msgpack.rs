@@ -0,0 +1,58 @@
+use crate::Value;
+
+pub use rmp_serde::decode::Error as MsgpackError;
+
+impl Value {
+    /// Encodes the value as MessagePack. Integers use the smallest encoding that holds them while
+    /// keeping their sign, floats are always encoded as 64-bit floats. A `Number::Big` has no
+    /// MessagePack form and is encoded as serde_json's private number struct.
+    pub fn to_msgpack(&self) -> Vec<u8> {
+        rmp_serde::to_vec(self).expect("encoding into a Vec cannot fail")
+    }
+
+    pub fn from_msgpack(bytes: &[u8]) -> Result<Value, MsgpackError> {
+        rmp_serde::from_slice(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Number;
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_nested_values() {
+        let value: Value = json!({
+            "name": "ann",
+            "scores": [1, -1, 1.5, u64::max_value(), i64::min_value()],
+            "nested": {"empty": {}, "list": [[], [null, true, false]]}
+        })
+        .into();
+        let bytes = value.to_msgpack();
+        assert_eq!(Value::from_msgpack(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn round_trips_large_array() {
+        let value = Value::from_array_iter((0..10_000u64).map(|n| Value::Number(n.into())));
+        let bytes = value.to_msgpack();
+        assert_eq!(Value::from_msgpack(&bytes).unwrap(), value);
+    }
+
+    #[test]
+    fn uses_compact_integers() {
+        assert_eq!(Value::Number(Number::PosInt(5)).to_msgpack(), vec![0x05]);
+        assert_eq!(Value::Number(Number::NegInt(-1)).to_msgpack(), vec![0xff]);
+        assert_eq!(
+            Value::Number(Number::PosInt(200)).to_msgpack(),
+            vec![0xcc, 200]
+        );
+        assert_eq!(Value::Number(Number::Float(1.0)).to_msgpack().len(), 9);
+    }
+
+    #[test]
+    fn rejects_truncated_input() {
+        assert!(Value::from_msgpack(&[0x92, 0x01]).is_err());
+    }
+}
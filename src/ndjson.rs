@@ -0,0 +1,101 @@
+use crate::{ParseError, Value};
+use serde_json::Value as JsonValue;
+use std::io::{BufRead, BufReader, Read};
+
+/// Reads newline-delimited JSON, producing one value per line. Blank lines are skipped. A malformed
+/// line produces an `Err` and reading carries on with the next line, unless `stop_on_error` is set.
+/// Read errors always end the stream.
+pub struct NdjsonReader<R> {
+    reader: BufReader<R>,
+    line: String,
+    line_number: usize,
+    stop_on_error: bool,
+    done: bool,
+}
+
+impl<R: Read> NdjsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        NdjsonReader {
+            reader: BufReader::new(reader),
+            line: String::new(),
+            line_number: 0,
+            stop_on_error: false,
+            done: false,
+        }
+    }
+
+    pub fn stop_on_error(mut self, stop: bool) -> Self {
+        self.stop_on_error = stop;
+        self
+    }
+
+    /// The number of lines read so far, which is the line of the most recently returned item.
+    pub fn line_number(&self) -> usize {
+        self.line_number
+    }
+}
+
+impl<R: Read> Iterator for NdjsonReader<R> {
+    type Item = Result<Value, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.line.clear();
+            match self.reader.read_line(&mut self.line) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.line_number += 1;
+                    if self.line.trim().is_empty() {
+                        continue;
+                    }
+                    let result = serde_json::from_str::<JsonValue>(&self.line)
+                        .map(Value::from)
+                        .map_err(ParseError::from);
+                    if result.is_err() && self.stop_on_error {
+                        self.done = true;
+                    }
+                    return Some(result);
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e.into()));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const INPUT: &[u8] = b"{\"a\": 1}\n\n[1, 2]\r\n{\"broken\": \n  \n\"last\"";
+
+    #[test]
+    fn reads_lines_past_errors() {
+        let mut reader = NdjsonReader::new(INPUT);
+        let expected: Value = json!({"a": 1}).into();
+        assert_eq!(reader.next().unwrap().unwrap(), expected);
+        assert_eq!(reader.line_number(), 1);
+        let expected: Value = json!([1, 2]).into();
+        assert_eq!(reader.next().unwrap().unwrap(), expected);
+        assert_eq!(reader.line_number(), 3);
+        assert!(matches!(reader.next(), Some(Err(ParseError::Syntax(_)))));
+        assert_eq!(reader.line_number(), 4);
+        assert_eq!(
+            reader.next().unwrap().unwrap(),
+            Value::String("last".to_owned())
+        );
+        assert!(reader.next().is_none());
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn stops_on_error() {
+        let results: Vec<_> = NdjsonReader::new(INPUT).stop_on_error(true).collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+    }
+}
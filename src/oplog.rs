@@ -0,0 +1,155 @@
+//! Recording mutations so they can be replayed onto a snapshot.
+
+use crate::Value;
+use serde::{Deserialize, Serialize};
+
+/// A single mutation, addressed by JSON Pointer.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum Op {
+    Set { path: String, value: Value },
+    Remove { path: String },
+}
+
+impl Op {
+    /// Applies the mutation, returning whether it changed anything.
+    pub fn apply(&self, target: &mut Value) -> bool {
+        match self {
+            Op::Set { path, value } => target.set_path(path, value.clone()),
+            Op::Remove { path } => target.remove_path(path).is_some(),
+        }
+    }
+}
+
+/// A value together with the mutations made to it since it was wrapped. Only mutations that
+/// succeed are recorded, so replaying the log onto the original value reproduces the current one.
+#[derive(Clone, Debug)]
+pub struct OpLog {
+    base: Value,
+    current: Value,
+    ops: Vec<Op>,
+}
+
+impl OpLog {
+    pub fn new(base: Value) -> Self {
+        OpLog {
+            current: base.clone(),
+            base,
+            ops: Vec::new(),
+        }
+    }
+
+    /// The value as it was when the log was created.
+    pub fn base(&self) -> &Value {
+        &self.base
+    }
+
+    /// The value with every recorded mutation applied.
+    pub fn current(&self) -> &Value {
+        &self.current
+    }
+
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    pub fn into_ops(self) -> Vec<Op> {
+        self.ops
+    }
+
+    /// See `Value::set_path`.
+    pub fn set_path(&mut self, path: &str, value: Value) -> bool {
+        self.record(Op::Set {
+            path: path.to_owned(),
+            value,
+        })
+    }
+
+    /// See `Value::remove_path`.
+    pub fn remove_path(&mut self, path: &str) -> Option<Value> {
+        let removed = self.current.remove_path(path)?;
+        self.ops.push(Op::Remove {
+            path: path.to_owned(),
+        });
+        Some(removed)
+    }
+
+    fn record(&mut self, op: Op) -> bool {
+        let applied = op.apply(&mut self.current);
+        if applied {
+            self.ops.push(op);
+        }
+        applied
+    }
+
+    /// Applies `ops` in order to a copy of `base`.
+    pub fn replay(base: &Value, ops: &[Op]) -> Value {
+        let mut value = base.clone();
+        for op in ops {
+            op.apply(&mut value);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn replays_onto_base() {
+        let base: Value = json!({"users": [{"name": "ann"}], "count": 1}).into();
+        let mut log = OpLog::new(base.clone());
+        assert!(log.set_path("/users/-", json!({"name": "bob"}).into()));
+        assert!(log.set_path("/count", Value::Number(2.into())));
+        assert!(log.set_path("/users/0/admin", Value::Bool(true)));
+        assert_eq!(
+            log.remove_path("/users/1/name"),
+            Some(Value::String("bob".to_owned()))
+        );
+        assert!(!log.set_path("/missing/key", Value::Null));
+        assert_eq!(log.remove_path("/missing"), None);
+        assert_eq!(log.ops().len(), 4);
+
+        let expected: Value = json!({
+            "users": [{"name": "ann", "admin": true}, {}],
+            "count": 2
+        })
+        .into();
+        assert_eq!(log.current(), &expected);
+        assert_eq!(log.base(), &base);
+        assert_eq!(OpLog::replay(&base, log.ops()), expected);
+    }
+
+    #[test]
+    fn serializes_ops() {
+        let mut log = OpLog::new(json!({}).into());
+        log.set_path("/a", Value::Number(1.into()));
+        log.remove_path("/a");
+        assert_eq!(
+            serde_json::to_string(log.ops()).unwrap(),
+            r#"[{"op":"set","path":"/a","value":1},{"op":"remove","path":"/a"}]"#
+        );
+    }
+
+    #[test]
+    fn replays_stored_ops() {
+        let base: Value = json!({"items": [1, 2], "meta": {"v": 1}}).into();
+        let mut log = OpLog::new(base.clone());
+        log.set_path(
+            "/items/-",
+            json!({"id": 3, "tags": ["x"], "score": -1.5}).into(),
+        );
+        log.remove_path("/items/0");
+        log.set_path("/meta", json!({"v": 2, "note": null}).into());
+
+        let stored = serde_json::to_string(log.ops()).unwrap();
+        let ops: Vec<Op> = serde_json::from_str(&stored).unwrap();
+        assert_eq!(ops, log.ops());
+        assert_eq!(&OpLog::replay(&base, &ops), log.current());
+
+        let value: Value = serde_json::from_str(r#"{"b": [true, null], "a": "x"}"#).unwrap();
+        assert_eq!(value, json!({"a": "x", "b": [true, null]}));
+    }
+}
@@ -0,0 +1,621 @@
+//! JSON Pointer (RFC 6901) support.
+
+use crate::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+
+/// Returned by `Value::require` when a pointer does not resolve.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MissingPathError {
+    /// The pointer that was looked up.
+    pub path: String,
+    /// The unescaped reference token that could not be followed, or the whole pointer if it is not
+    /// a valid JSON Pointer.
+    pub segment: String,
+    /// The longest prefix of the pointer that did resolve.
+    pub resolved: String,
+}
+
+impl fmt::Display for MissingPathError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "{}: \"{}\" not found under \"{}\"",
+            self.path, self.segment, self.resolved
+        )
+    }
+}
+
+impl Error for MissingPathError {}
+
+pub(crate) fn escape(token: &str) -> String {
+    token.replace('~', "~0").replace('/', "~1")
+}
+
+fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// Splits a pointer into its unescaped reference tokens, or `None` if it is not a valid pointer.
+pub(crate) fn parse(pointer: &str) -> Option<Vec<String>> {
+    if pointer.is_empty() {
+        Some(Vec::new())
+    } else if pointer.starts_with('/') {
+        Some(pointer[1..].split('/').map(unescape).collect())
+    } else {
+        None
+    }
+}
+
+/// Array indices are written in decimal without a sign or leading zeros.
+pub(crate) fn parse_index(token: &str) -> Option<usize> {
+    if token.starts_with('+') || (token.starts_with('0') && token.len() > 1) {
+        None
+    } else {
+        token.parse().ok()
+    }
+}
+
+pub(crate) fn step<'v>(value: &'v Value, token: &str) -> Option<&'v Value> {
+    match value {
+        Value::Object(obj) => obj.get(token),
+        Value::Array(arr) => arr.get(parse_index(token)?),
+        _ => None,
+    }
+}
+
+pub(crate) fn step_mut<'v>(value: &'v mut Value, token: &str) -> Option<&'v mut Value> {
+    match value {
+        Value::Object(obj) => obj.get_mut(token),
+        Value::Array(arr) => arr.get_mut(parse_index(token)?),
+        _ => None,
+    }
+}
+
+/// Compares the nodes at `path`, either of which may be missing, skipping the pointers in `ignore`.
+fn eq_ignoring_at(
+    a: Option<&Value>,
+    b: Option<&Value>,
+    path: &mut String,
+    ignore: &[&str],
+) -> bool {
+    if ignore.contains(&path.as_str()) {
+        return true;
+    }
+    let child = |path: &mut String, token: &str, a, b| {
+        let len = path.len();
+        path.push('/');
+        path.push_str(token);
+        let equal = eq_ignoring_at(a, b, path, ignore);
+        path.truncate(len);
+        equal
+    };
+    match (a, b) {
+        (Some(Value::Object(a)), Some(Value::Object(b))) => {
+            a.iter()
+                .all(|(key, value)| child(path, &escape(key), Some(value), b.get(key.as_str())))
+                && b.iter()
+                    .filter(|(key, _)| !a.contains_key(key.as_str()))
+                    .all(|(key, value)| child(path, &escape(key), None, Some(value)))
+        }
+        (Some(Value::Array(a)), Some(Value::Array(b))) => (0..a.len().max(b.len()))
+            .all(|idx| child(path, &idx.to_string(), a.get(idx), b.get(idx))),
+        (a, b) => a == b,
+    }
+}
+
+/// Every node of a document with its pointer, in document order or breadth first. The walk keeps
+/// its own queue so deeply nested documents do not overflow the call stack.
+pub(crate) struct Nodes<'a> {
+    queue: VecDeque<(String, &'a Value)>,
+    breadth_first: bool,
+}
+
+impl<'a> Nodes<'a> {
+    pub(crate) fn new(root: &'a Value) -> Self {
+        Nodes {
+            queue: vec![(String::new(), root)].into(),
+            breadth_first: false,
+        }
+    }
+
+    pub(crate) fn breadth_first(root: &'a Value) -> Self {
+        Nodes {
+            breadth_first: true,
+            ..Nodes::new(root)
+        }
+    }
+}
+
+impl<'a> Iterator for Nodes<'a> {
+    type Item = (String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (path, value) = if self.breadth_first {
+            self.queue.pop_front()?
+        } else {
+            self.queue.pop_back()?
+        };
+        let mut children = Vec::new();
+        match value {
+            Value::Array(arr) => {
+                for (idx, child) in arr.iter().enumerate() {
+                    children.push((format!("{}/{}", path, idx), child));
+                }
+            }
+            Value::Object(obj) => {
+                for (key, child) in obj.iter() {
+                    children.push((format!("{}/{}", path, escape(key)), child));
+                }
+            }
+            _ => {}
+        }
+        // Depth first pops from the back, so children go on in reverse to come off in order.
+        if self.breadth_first {
+            self.queue.extend(children);
+        } else {
+            self.queue.extend(children.into_iter().rev());
+        }
+        Some((path, value))
+    }
+}
+
+impl Value {
+    /// Looks up a value by JSON Pointer, such as `/users/0/name`. The empty pointer refers to
+    /// `self`.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        parse(pointer)?
+            .iter()
+            .try_fold(self, |value, token| step(value, token))
+    }
+
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        parse(pointer)?
+            .iter()
+            .try_fold(self, |value, token| step_mut(value, token))
+    }
+
+    /// Replaces the value at each JSON Pointer in `paths` with `mask`, skipping pointers that do
+    /// not resolve. Only the containers along the redacted paths are copied.
+    pub fn redact(&mut self, paths: &[&str], mask: &Value) {
+        for path in paths {
+            if let Some(target) = self.pointer_mut(path) {
+                *target = mask.clone();
+            }
+        }
+    }
+
+    /// Deep equality that skips the nodes at the JSON Pointers in `ignore` on both sides. An
+    /// ignored node may be missing from either side, but any other node must be present on both.
+    pub fn eq_ignoring(&self, other: &Value, ignore: &[&str]) -> bool {
+        eq_ignoring_at(Some(self), Some(other), &mut String::new(), ignore)
+    }
+
+    /// Returns the first node, in document order, that satisfies `pred`, together with its JSON
+    /// Pointer. Parents come before their children, so `self` is checked first.
+    pub fn find<F>(&self, pred: F) -> Option<(String, &Value)>
+    where
+        F: Fn(&Value) -> bool,
+    {
+        Nodes::new(self).find(|(_, value)| pred(*value))
+    }
+
+    /// Returns every node that satisfies `pred`, each with its JSON Pointer, in document order.
+    pub fn find_all<F>(&self, pred: F) -> Vec<(String, &Value)>
+    where
+        F: Fn(&Value) -> bool,
+    {
+        Nodes::new(self).filter(|(_, value)| pred(*value)).collect()
+    }
+
+    /// Lazily yields every node with its pointer, depth first in document order, so each node is
+    /// followed by its descendants before its next sibling.
+    pub fn iter_dfs(&self) -> impl Iterator<Item = (String, &Value)> + '_ {
+        Nodes::new(self)
+    }
+
+    /// Lazily yields every node with its pointer, breadth first, so all nodes at one depth come
+    /// before any deeper node. Nodes at the same depth are in document order.
+    pub fn iter_bfs(&self) -> impl Iterator<Item = (String, &Value)> + '_ {
+        Nodes::breadth_first(self)
+    }
+
+    /// Lazily yields the pointer to every leaf in document order. Empty arrays and objects count as
+    /// leaves, so every part of the document is covered by some path. A scalar root yields the
+    /// empty pointer.
+    pub fn leaf_paths(&self) -> impl Iterator<Item = String> + '_ {
+        Nodes::new(self).filter_map(|(path, value)| match value {
+            Value::Array(arr) if arr.len() > 0 => None,
+            Value::Object(obj) if !obj.is_empty() => None,
+            _ => Some(path),
+        })
+    }
+
+    /// Sketches the layout of a document as its leaf paths and their types, sorted by path. Paths
+    /// are written like pointers, except that all elements of an array share one path ending in
+    /// `[]`, such as `/users[]/name`. A path whose leaves have differing types is `"mixed"`. As for
+    /// `leaf_paths`, empty arrays and objects are leaves.
+    pub fn infer_shape(&self) -> Vec<(String, &'static str)> {
+        let mut shape = BTreeMap::new();
+        let mut stack = vec![(String::new(), self)];
+        while let Some((path, value)) = stack.pop() {
+            match value {
+                Value::Array(arr) if arr.len() > 0 => {
+                    let child = format!("{}[]", path);
+                    stack.extend(arr.iter().map(|item| (child.clone(), item)));
+                }
+                Value::Object(obj) if !obj.is_empty() => {
+                    for (key, child) in obj.iter() {
+                        stack.push((format!("{}/{}", path, escape(key)), child));
+                    }
+                }
+                _ => {
+                    let kind = value.type_name();
+                    let entry = shape.entry(path).or_insert(kind);
+                    if *entry != kind {
+                        *entry = "mixed";
+                    }
+                }
+            }
+        }
+        shape.into_iter().collect()
+    }
+
+    /// Like `pointer`, but reports which reference token failed to resolve.
+    pub fn require(&self, pointer: &str) -> Result<&Value, MissingPathError> {
+        let missing = |segment: &str, resolved: &str| MissingPathError {
+            path: pointer.to_owned(),
+            segment: segment.to_owned(),
+            resolved: resolved.to_owned(),
+        };
+        let tokens = parse(pointer).ok_or_else(|| missing(pointer, ""))?;
+        let mut value = self;
+        let mut resolved = String::new();
+        for token in &tokens {
+            value = step(value, token).ok_or_else(|| missing(token, &resolved))?;
+            resolved.push('/');
+            resolved.push_str(&escape(token));
+        }
+        Ok(value)
+    }
+
+    /// Stores `value` at a JSON Pointer. The parent must already exist: an object parent gains the
+    /// key if it is missing, while an array parent accepts an existing index, its length or `-` to
+    /// append. The empty pointer replaces `self`. Returns false, leaving the document unchanged,
+    /// if the pointer cannot be set.
+    pub fn set_path(&mut self, pointer: &str, value: Value) -> bool {
+        let mut tokens = match parse(pointer) {
+            Some(tokens) => tokens,
+            None => return false,
+        };
+        let last = match tokens.pop() {
+            Some(last) => last,
+            None => {
+                *self = value;
+                return true;
+            }
+        };
+        let parent = tokens
+            .iter()
+            .try_fold(self, |value, token| step_mut(value, token));
+        match parent {
+            Some(Value::Object(obj)) => {
+                obj.insert(last, value);
+                true
+            }
+            Some(Value::Array(arr)) => {
+                let idx = if last == "-" {
+                    arr.len()
+                } else {
+                    match parse_index(&last) {
+                        Some(idx) => idx,
+                        None => return false,
+                    }
+                };
+                if idx < arr.len() {
+                    *arr.get_mut(idx).unwrap() = value;
+                } else if idx == arr.len() {
+                    arr.push_back(value);
+                } else {
+                    return false;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Removes the value at a JSON Pointer from its parent and returns it. Removing an array
+    /// element shifts the elements after it down by one. The root cannot be removed, so the empty
+    /// pointer returns `None`.
+    pub fn remove_path(&mut self, pointer: &str) -> Option<Value> {
+        let mut tokens = parse(pointer)?;
+        let last = tokens.pop()?;
+        let parent = tokens
+            .iter()
+            .try_fold(self, |value, token| step_mut(value, token))?;
+        match parent {
+            Value::Object(obj) => obj.remove(last.as_str()),
+            Value::Array(arr) => {
+                let idx = parse_index(&last)?;
+                if idx < arr.len() {
+                    arr.remove(idx)
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn document() -> Value {
+        json!({
+            "users": [
+                {"name": "ann", "tags": ["a", "b", "c"]},
+                {"name": "bob", "a/b": 1, "m~n": 2}
+            ],
+            "": 3
+        })
+        .into()
+    }
+
+    #[test]
+    fn looks_up_pointers() {
+        let mut value = document();
+        assert_eq!(value.pointer(""), Some(&value.clone()));
+        assert_eq!(
+            value.pointer("/users/0/tags/2").and_then(Value::as_str),
+            Some("c")
+        );
+        assert_eq!(
+            value.pointer("/users/1/a~1b"),
+            Some(&Value::Number(1.into()))
+        );
+        assert_eq!(
+            value.pointer("/users/1/m~0n"),
+            Some(&Value::Number(2.into()))
+        );
+        assert_eq!(value.pointer("/"), Some(&Value::Number(3.into())));
+        assert_eq!(value.pointer("/users/01"), None);
+        assert_eq!(value.pointer("/users/2"), None);
+        assert_eq!(value.pointer("users"), None);
+
+        *value.pointer_mut("/users/1/name").unwrap() = Value::Null;
+        assert_eq!(value.pointer("/users/1/name"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn finds_first_string() {
+        let value: Value = json!({
+            "a": [1, {"b": null, "c": [true, "deep", "later"]}],
+            "d": "last"
+        })
+        .into();
+        let (path, found) = value.find(Value::is_string).unwrap();
+        assert_eq!(path, "/a/1/c/1");
+        assert_eq!(found.as_str(), Some("deep"));
+        assert_eq!(value.find(|_| true).unwrap().0, "");
+        let one = Value::Number(1.into());
+        assert_eq!(document().find(|v| *v == one).unwrap().0, "/users/1/a~1b");
+        assert_eq!(value.find(|v| *v == Value::Number(2.into())), None);
+    }
+
+    #[test]
+    fn finds_all_numbers() {
+        let value: Value = json!({
+            "a": [1, {"b": 2.5, "c": [3, "four"]}],
+            "d": {"e": -5, "f": null},
+            "g": 6
+        })
+        .into();
+        let found = value.find_all(Value::is_number);
+        let paths: Vec<&str> = found.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["/a/0", "/a/1/b", "/a/1/c/0", "/d/e", "/g"]);
+        let numbers: Value = found.into_iter().map(|(_, value)| value.clone()).collect();
+        let expected: Value = json!([1, 2.5, 3, -5, 6]).into();
+        assert_eq!(numbers, expected);
+        assert!(value.find_all(Value::is_boolean).is_empty());
+    }
+
+    #[test]
+    fn lists_leaf_paths() {
+        let paths: Vec<String> = document().leaf_paths().collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/",
+                "/users/0/name",
+                "/users/0/tags/0",
+                "/users/0/tags/1",
+                "/users/0/tags/2",
+                "/users/1/a~1b",
+                "/users/1/m~0n",
+                "/users/1/name",
+            ]
+        );
+        let value: Value = json!({"a": [], "b": {}, "c": [{}]}).into();
+        let paths: Vec<String> = value.leaf_paths().collect();
+        assert_eq!(paths, vec!["/a", "/b", "/c/0"]);
+        assert_eq!(Value::Null.leaf_paths().collect::<Vec<_>>(), vec![""]);
+    }
+
+    #[test]
+    fn infers_shape_of_records() {
+        let value: Value = json!({
+            "users": [
+                {"name": "ada", "age": 36, "tags": ["a"]},
+                {"name": "bob", "age": "unknown", "tags": []},
+                {"name": "eve", "a/b": null}
+            ],
+            "total": 3
+        })
+        .into();
+        assert_eq!(
+            value.infer_shape(),
+            vec![
+                ("/total".to_owned(), "number"),
+                ("/users[]/a~1b".to_owned(), "null"),
+                ("/users[]/age".to_owned(), "mixed"),
+                ("/users[]/name".to_owned(), "string"),
+                ("/users[]/tags".to_owned(), "array"),
+                ("/users[]/tags[]".to_owned(), "string"),
+            ]
+        );
+        assert_eq!(
+            Value::Bool(true).infer_shape(),
+            vec![(String::new(), "boolean")]
+        );
+    }
+
+    #[test]
+    fn iterates_depth_and_breadth_first() {
+        let value: Value = json!({"a": {"c": [1, 2]}, "b": {"d": 3}}).into();
+        let dfs: Vec<String> = value.iter_dfs().map(|(path, _)| path).collect();
+        assert_eq!(
+            dfs,
+            vec!["", "/a", "/a/c", "/a/c/0", "/a/c/1", "/b", "/b/d"]
+        );
+        let bfs: Vec<String> = value.iter_bfs().map(|(path, _)| path).collect();
+        assert_eq!(
+            bfs,
+            vec!["", "/a", "/b", "/a/c", "/b/d", "/a/c/0", "/a/c/1"]
+        );
+
+        for (path, node) in value.iter_bfs() {
+            assert_eq!(value.pointer(&path), Some(node));
+        }
+        assert_eq!(Value::Null.iter_bfs().count(), 1);
+    }
+
+    #[test]
+    fn sets_pointers() {
+        let mut value = document();
+        assert!(value.set_path("/users/1/a~1b", Value::Null));
+        assert!(value.set_path("/users/0/tags/3", Value::String("d".to_owned())));
+        assert!(value.set_path("/users/0/tags/-", Value::String("e".to_owned())));
+        assert!(value.set_path("/users/0/tags/0", Value::String("z".to_owned())));
+        assert!(value.set_path("/new", Value::Bool(true)));
+        assert!(!value.set_path("/users/0/tags/9", Value::Null));
+        assert!(!value.set_path("/absent/key", Value::Null));
+        assert!(!value.set_path("/users/0/name/x", Value::Null));
+        assert!(!value.set_path("users", Value::Null));
+
+        assert_eq!(value["users"][1]["a/b"], Value::Null);
+        let expected: Value = json!(["z", "b", "c", "d", "e"]).into();
+        assert_eq!(value["users"][0]["tags"], expected);
+        assert_eq!(value["new"], Value::Bool(true));
+
+        assert!(value.set_path("", Value::Null));
+        assert_eq!(value, Value::Null);
+    }
+
+    #[test]
+    fn redacts_paths() {
+        let mut value: Value = json!({
+            "user": {"name": "ann", "ssn": "123-45-6789", "email": "ann@example.com"},
+            "history": [{"ssn": "kept"}]
+        })
+        .into();
+        let before = value.clone();
+        let mask = Value::String("***".to_owned());
+        value.redact(
+            &["/user/ssn", "/user/email", "/user/phone", "/missing/ssn"],
+            &mask,
+        );
+        assert_eq!(
+            value,
+            json!({
+                "user": {"name": "ann", "ssn": "***", "email": "***"},
+                "history": [{"ssn": "kept"}]
+            })
+        );
+        assert!(value["history"].ptr_eq(&before["history"]));
+    }
+
+    #[test]
+    fn compares_ignoring_paths() {
+        let a: Value = json!({
+            "id": 1,
+            "meta": {"created": "2024-01-01T00:00:00Z", "version": 2},
+            "items": [{"name": "x", "ts": 1}, {"name": "y", "ts": 2}]
+        })
+        .into();
+        let b: Value = json!({
+            "id": 1,
+            "meta": {"version": 2},
+            "items": [{"name": "x", "ts": 10}, {"name": "y", "ts": 20}]
+        })
+        .into();
+        assert_ne!(a, b);
+        let ignore = ["/meta/created", "/items/0/ts", "/items/1/ts"];
+        assert!(a.eq_ignoring(&b, &ignore));
+        assert!(b.eq_ignoring(&a, &ignore));
+        assert!(!a.eq_ignoring(&b, &ignore[..2]));
+        assert!(!a.eq_ignoring(&b, &["/meta/created"]));
+        assert!(a.eq_ignoring(&b, &["/meta", "/items"]));
+        assert!(a.eq_ignoring(&Value::Null, &[""]));
+
+        let mut c = a.clone();
+        c["meta"]["extra"] = Value::Bool(true);
+        assert!(!a.eq_ignoring(&c, &ignore));
+        assert!(a.eq_ignoring(&c, &["/meta/extra"]));
+    }
+
+    #[test]
+    fn requires_paths() {
+        let value = document();
+        assert_eq!(
+            value.require("/users/0/name").unwrap().as_str(),
+            Some("ann")
+        );
+
+        let error = value.require("/users/1/address/city").unwrap_err();
+        assert_eq!(error.segment, "address");
+        assert_eq!(error.resolved, "/users/1");
+        assert_eq!(
+            error.to_string(),
+            "/users/1/address/city: \"address\" not found under \"/users/1\""
+        );
+
+        let error = value.require("/users/0/tags/3").unwrap_err();
+        assert_eq!(error.path, "/users/0/tags/3");
+        assert_eq!(error.segment, "3");
+        assert_eq!(error.resolved, "/users/0/tags");
+
+        let error = value.require("users").unwrap_err();
+        assert_eq!(error.segment, "users");
+        assert_eq!(error.resolved, "");
+    }
+
+    #[test]
+    fn removes_object_key() {
+        let mut value = document();
+        assert_eq!(
+            value.remove_path("/users/1/a~1b"),
+            Some(Value::Number(1.into()))
+        );
+        assert_eq!(value.remove_path("/users/1/a~1b"), None);
+        let expected: Value = json!({"name": "bob", "m~n": 2}).into();
+        assert_eq!(value["users"][1], expected);
+    }
+
+    #[test]
+    fn removes_array_element() {
+        let mut value = document();
+        assert_eq!(
+            value.remove_path("/users/0/tags/1"),
+            Some(Value::String("b".to_owned()))
+        );
+        let expected: Value = json!(["a", "c"]).into();
+        assert_eq!(value["users"][0]["tags"], expected);
+        assert_eq!(value.remove_path("/users/0/tags/2"), None);
+        assert_eq!(value.remove_path(""), None);
+    }
+}
@@ -0,0 +1,85 @@
+//! URL query strings built from flat objects.
+
+use crate::Value;
+use std::fmt::Write;
+
+/// Percent-encodes everything but the unreserved characters of RFC 3986, so spaces become `%20`.
+fn push_encoded(out: &mut String, s: &str) {
+    for byte in s.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                out.push(byte as char)
+            }
+            _ => write!(out, "%{:02X}", byte).unwrap(),
+        }
+    }
+}
+
+/// The text of a scalar in a query string, or `None` for an array or object.
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::new()),
+        Value::Bool(b) => Some(b.to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::String(s) => Some(s.clone()),
+        Value::Array(_) | Value::Object(_) => None,
+    }
+}
+
+impl Value {
+    /// Encodes a flat object as `key=value` pairs joined by `&`, in key order, with keys and values
+    /// percent-encoded. Strings are written without quotes and `null` as an empty value. An array
+    /// of scalars repeats its key once per element, as in `tag=a&tag=b`, so an empty array adds
+    /// nothing. Returns `None` if `self` is not an object or holds a nested object or array.
+    pub fn to_query_string(&self) -> Option<String> {
+        let mut query = String::new();
+        for (key, value) in self.as_object()?.iter() {
+            let texts = match value {
+                Value::Array(arr) => arr.iter().map(scalar_text).collect::<Option<Vec<_>>>()?,
+                _ => vec![scalar_text(value)?],
+            };
+            for text in texts {
+                if !query.is_empty() {
+                    query.push('&');
+                }
+                push_encoded(&mut query, key);
+                query.push('=');
+                push_encoded(&mut query, &text);
+            }
+        }
+        Some(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn encodes_flat_objects() {
+        let value: Value = json!({
+            "q": "rust & json",
+            "page": 2,
+            "exact": true,
+            "empty": null,
+            "tag": ["a/b", "é"],
+            "none": []
+        })
+        .into();
+        assert_eq!(
+            value.to_query_string().unwrap(),
+            "empty=&exact=true&page=2&q=rust%20%26%20json&tag=a%2Fb&tag=%C3%A9"
+        );
+        assert_eq!(Value::new_object().to_query_string(), Some(String::new()));
+    }
+
+    #[test]
+    fn rejects_nested_values() {
+        let nested: Value = json!({"a": 1, "b": {"c": 2}}).into();
+        assert_eq!(nested.to_query_string(), None);
+        let nested_array: Value = json!({"a": [[1]]}).into();
+        assert_eq!(nested_array.to_query_string(), None);
+        assert_eq!(Value::from("a=b").to_query_string(), None);
+    }
+}
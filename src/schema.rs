@@ -0,0 +1,227 @@
+//! Validation against a subset of JSON Schema.
+//!
+//! The supported keywords are `type` (a single name or a list of names, including `integer`),
+//! `enum`, `minimum`, `maximum`, `required`, `properties` and `items`, where `items` must be a
+//! single schema that is applied to every element. Any other keyword is ignored, as is a schema
+//! that is not an object.
+
+use crate::pointer;
+use crate::{Number, Value};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ValidationError {
+    /// JSON Pointer to the offending node.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}: {}", self.path, self.message)
+    }
+}
+
+impl Error for ValidationError {}
+
+fn matches_type(value: &Value, name: &str) -> bool {
+    match (name, value) {
+        ("integer", Value::Number(Number::Float(f))) => f.fract() == 0.0,
+        #[cfg(feature = "arbitrary_precision")]
+        ("integer", Value::Number(Number::Big(s))) => {
+            !s.contains(|c| c == '.' || c == 'e' || c == 'E')
+        }
+        ("integer", Value::Number(_)) => true,
+        _ => value.type_name() == name,
+    }
+}
+
+fn error(errors: &mut Vec<ValidationError>, path: &str, message: String) {
+    errors.push(ValidationError {
+        path: path.to_owned(),
+        message,
+    });
+}
+
+fn validate_node(
+    value: &Value,
+    schema: &Value,
+    path: &mut String,
+    errors: &mut Vec<ValidationError>,
+) {
+    let schema = match schema.as_object() {
+        Some(schema) => schema,
+        None => return,
+    };
+
+    match schema.get("type") {
+        Some(Value::String(name)) if !matches_type(value, name) => error(
+            errors,
+            path,
+            format!("expected {}, found {}", name, value.type_name()),
+        ),
+        Some(Value::Array(names))
+            if !names.iter().any(|name| {
+                name.as_str()
+                    .map_or(false, |name| matches_type(value, name))
+            }) =>
+        {
+            let names: Vec<&str> = names.iter().filter_map(Value::as_str).collect();
+            error(
+                errors,
+                path,
+                format!(
+                    "expected {}, found {}",
+                    names.join(" or "),
+                    value.type_name()
+                ),
+            )
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(options)) = schema.get("enum") {
+        if !options.iter().any(|option| option == value) {
+            error(
+                errors,
+                path,
+                "value is not one of the enumerated values".to_owned(),
+            );
+        }
+    }
+
+    if let Value::Number(n) = value {
+        let n = n.to_f64_lossy();
+        if let Some(Value::Number(minimum)) = schema.get("minimum") {
+            if n < minimum.to_f64_lossy() {
+                error(
+                    errors,
+                    path,
+                    format!("{} is less than the minimum of {}", n, minimum),
+                );
+            }
+        }
+        if let Some(Value::Number(maximum)) = schema.get("maximum") {
+            if n > maximum.to_f64_lossy() {
+                error(
+                    errors,
+                    path,
+                    format!("{} is greater than the maximum of {}", n, maximum),
+                );
+            }
+        }
+    }
+
+    if let Value::Object(obj) = value {
+        if let Some(Value::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(Value::as_str) {
+                if !obj.contains_key(key) {
+                    error(
+                        errors,
+                        path,
+                        format!("missing required property \"{}\"", key),
+                    );
+                }
+            }
+        }
+        if let Some(Value::Object(properties)) = schema.get("properties") {
+            for (key, subschema) in properties.iter() {
+                if let Some(child) = obj.get(key.as_str()) {
+                    let len = path.len();
+                    path.push('/');
+                    path.push_str(&pointer::escape(key));
+                    validate_node(child, subschema, path, errors);
+                    path.truncate(len);
+                }
+            }
+        }
+    }
+
+    if let (Value::Array(arr), Some(items)) = (value, schema.get("items")) {
+        for (idx, child) in arr.iter().enumerate() {
+            let len = path.len();
+            path.push('/');
+            path.push_str(&idx.to_string());
+            validate_node(child, items, path, errors);
+            path.truncate(len);
+        }
+    }
+}
+
+impl Value {
+    /// Validates this value against a schema written in the subset of JSON Schema described in the
+    /// `schema` module, collecting every violation found.
+    pub fn validate(&self, schema: &Value) -> Result<(), Vec<ValidationError>> {
+        let mut errors = Vec::new();
+        validate_node(self, schema, &mut String::new(), &mut errors);
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "required": ["name", "address"],
+            "properties": {
+                "name": {"type": "string"},
+                "age": {"type": "integer", "minimum": 0, "maximum": 150},
+                "address": {
+                    "type": "object",
+                    "required": ["city"],
+                    "properties": {
+                        "city": {"type": "string"},
+                        "kind": {"enum": ["home", "work"]}
+                    }
+                },
+                "tags": {"type": "array", "items": {"type": ["string", "null"]}}
+            }
+        })
+        .into()
+    }
+
+    #[test]
+    fn accepts_valid_document() {
+        let value: Value = json!({
+            "name": "ann",
+            "age": 31,
+            "address": {"city": "Cape Town", "kind": "home"},
+            "tags": ["a", null]
+        })
+        .into();
+        assert_eq!(value.validate(&schema()), Ok(()));
+    }
+
+    #[test]
+    fn reports_invalid_document() {
+        let value: Value = json!({
+            "name": 5,
+            "age": -1.5,
+            "address": {"kind": "other"},
+            "tags": ["a", 1]
+        })
+        .into();
+        let errors = value.validate(&schema()).unwrap_err();
+        let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                "/address",
+                "/address/kind",
+                "/age",
+                "/age",
+                "/name",
+                "/tags/1"
+            ]
+        );
+    }
+}
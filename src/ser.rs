@@ -0,0 +1,147 @@
+//! `serde` serialization. Objects are written in key order, or in insertion order if they were
+//! created with `Object::with_insertion_order`.
+
+use crate::{Number, Object, Value};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+
+impl Serialize for Number {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Number::PosInt(u) => serializer.serialize_u64(*u),
+            Number::NegInt(i) => serializer.serialize_i64(*i),
+            Number::Float(f) => serializer.serialize_f64(*f),
+            #[cfg(feature = "arbitrary_precision")]
+            Number::Big(s) => s
+                .parse::<serde_json::Number>()
+                .map_err(serde::ser::Error::custom)?
+                .serialize(serializer),
+        }
+    }
+}
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        for (key, value) in self.iter_insertion_order() {
+            map.serialize_entry(key, value)?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::Null => serializer.serialize_unit(),
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Number(n) => n.serialize(serializer),
+            Value::String(s) => serializer.serialize_str(s),
+            Value::Array(arr) => serializer.collect_seq(arr.iter()),
+            Value::Object(obj) => obj.serialize(serializer),
+        }
+    }
+}
+
+impl Value {
+    /// Serializes compact JSON that is safe to embed in an HTML `<script>` element: `<`, `>` and
+    /// `&` are written as Unicode escapes, as are the line and paragraph separators U+2028 and
+    /// U+2029, which older JavaScript engines reject inside string literals.
+    pub fn to_string_html_safe(&self) -> String {
+        let json = serde_json::to_string(self).expect("serializing a Value cannot fail");
+        // These characters can only occur inside string literals, so escaping them anywhere in
+        // the output keeps it valid JSON.
+        let mut out = String::with_capacity(json.len());
+        for c in json.chars() {
+            match c {
+                '<' => out.push_str("\\u003c"),
+                '>' => out.push_str("\\u003e"),
+                '&' => out.push_str("\\u0026"),
+                '\u{2028}' => out.push_str("\\u2028"),
+                '\u{2029}' => out.push_str("\\u2029"),
+                c => out.push(c),
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_in_key_order() {
+        let value: Value = json!({"b": [1, -2, 2.5], "a": {"d": null, "c": "x"}, "e": true}).into();
+        assert_eq!(
+            serde_json::to_string(&value).unwrap(),
+            r#"{"a":{"c":"x","d":null},"b":[1,-2,2.5],"e":true}"#
+        );
+    }
+
+    #[test]
+    fn serializes_in_insertion_order() {
+        let mut obj = Object::with_insertion_order();
+        obj.insert("zeta".to_owned(), Value::Number(1.into()));
+        obj.insert("alpha".to_owned(), Value::Number(2.into()));
+        obj.insert("mid".to_owned(), Value::Number(3.into()));
+        obj.insert("alpha".to_owned(), Value::Number(4.into()));
+        obj.entry("beta").or_insert(Value::Null);
+        obj.get_or_insert_with("omega", || Value::Bool(true));
+        obj.remove("mid");
+        obj.insert("mid".to_owned(), Value::Number(5.into()));
+        obj.retain(|key, _| key != "beta");
+        assert_eq!(
+            serde_json::to_string(&obj).unwrap(),
+            r#"{"zeta":1,"alpha":4,"omega":true,"mid":5}"#
+        );
+
+        let mut tail = Object::with_insertion_order();
+        tail.insert("y".to_owned(), Value::Null);
+        tail.insert("x".to_owned(), Value::Null);
+        obj.append(&mut tail);
+        assert_eq!(
+            serde_json::to_string(&obj).unwrap(),
+            r#"{"zeta":1,"alpha":4,"omega":true,"mid":5,"y":null,"x":null}"#
+        );
+        assert!(tail.is_empty());
+
+        obj.remove_unordered("zeta");
+        obj.remove_unordered("y");
+        obj.repair();
+        assert!(obj.tracks_insertion_order());
+        assert_eq!(obj.validate_invariants(), Ok(()));
+        assert_eq!(
+            serde_json::to_string(&obj).unwrap(),
+            r#"{"alpha":4,"omega":true,"mid":5,"x":null}"#
+        );
+
+        obj.rename_keys(|key| match key {
+            "alpha" => Some("z".to_owned()),
+            "x" => Some("mid".to_owned()),
+            _ => None,
+        });
+        obj.insert("new".to_owned(), Value::Null);
+        assert_eq!(
+            serde_json::to_string(&obj).unwrap(),
+            r#"{"z":4,"omega":true,"mid":null,"new":null}"#
+        );
+    }
+
+    #[test]
+    fn escapes_for_html() {
+        let value: Value = json!({
+            "</script>": "<script>alert('x') && 1 > 0</script>",
+            "lines": "a\u{2028}b\u{2029}c"
+        })
+        .into();
+        let html = value.to_string_html_safe();
+        for c in &['<', '>', '&', '\u{2028}', '\u{2029}'] {
+            assert!(!html.contains(*c), "{} contains {:?}", html, c);
+        }
+        assert!(html.contains(r"\u003c/script\u003e"));
+        assert!(html.contains(r"\u0026\u0026"));
+        assert!(html.contains(r"a\u2028b\u2029c"));
+        assert_eq!(Value::from_str_borrowed(&html).unwrap(), value);
+    }
+}
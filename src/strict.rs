@@ -0,0 +1,234 @@
+//! Parsing with extra checks for untrusted input. The same visitor, making none of the checks,
+//! implements `Deserialize` for `Value`.
+
+use crate::{Number, Object, ParseError, Value};
+use librrb::Vector;
+use serde::de::{self, Deserialize, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use std::fmt;
+
+/// Selects the checks `Value::from_str_strict` makes on top of ordinary parsing. The default makes
+/// none of them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Fail on an object with a repeated key, instead of keeping the last entry.
+    pub reject_duplicate_keys: bool,
+    /// Fail on anything but whitespace after the document, instead of ignoring it.
+    pub reject_trailing: bool,
+    /// Fail on arrays and objects nested more than this many levels deep, counting the root.
+    /// serde_json's own limit of 128 levels applies regardless.
+    pub max_depth: Option<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct StrictSeed<'o> {
+    opts: &'o ParseOptions,
+    depth: usize,
+}
+
+impl<'o> StrictSeed<'o> {
+    fn nested<E: de::Error>(self) -> Result<Self, E> {
+        let depth = self.depth + 1;
+        match self.opts.max_depth {
+            Some(max) if depth > max => Err(E::custom(format!(
+                "nesting exceeds the maximum depth of {}",
+                max
+            ))),
+            _ => Ok(StrictSeed { depth, ..self }),
+        }
+    }
+}
+
+impl<'de, 'o> DeserializeSeed<'de> for StrictSeed<'o> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de, 'o> Visitor<'de> for StrictSeed<'o> {
+    type Value = Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any valid JSON value")
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+        Ok(Value::Number(n.into()))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Number(n.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Value, E> {
+        Ok(Value::Number(Number::Float(n)))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let seed = self.nested()?;
+        let mut items = Vector::new();
+        while let Some(item) = seq.next_element_seed(seed)? {
+            items.push_back(item);
+        }
+        Ok(Value::Array(items))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut next_key = map.next_key::<String>()?;
+        #[cfg(feature = "arbitrary_precision")]
+        {
+            if next_key.as_deref() == Some(crate::NUMBER_TOKEN) {
+                let text = map.next_value::<String>()?;
+                return crate::number_from_token(&text).map(Value::Number);
+            }
+        }
+        let seed = self.nested()?;
+        let mut entries = Vec::new();
+        while let Some(key) = next_key {
+            entries.push((key, map.next_value_seed(seed)?));
+            next_key = map.next_key::<String>()?;
+        }
+        if self.opts.reject_duplicate_keys {
+            let mut keys: Vec<&String> = entries.iter().map(|(key, _)| key).collect();
+            keys.sort_unstable();
+            if let Some(pair) = keys.windows(2).find(|pair| pair[0] == pair[1]) {
+                return Err(de::Error::custom(format!("duplicate key \"{}\"", pair[0])));
+            }
+        }
+        Ok(Value::Object(Object::from_entries(entries)))
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Value, D::Error> {
+        let seed = StrictSeed {
+            opts: &ParseOptions::default(),
+            depth: 0,
+        };
+        seed.deserialize(deserializer)
+    }
+}
+
+impl Value {
+    /// Parses a JSON document, applying the checks selected by `opts`. Errors are reported as
+    /// `ParseError::Syntax` with the position where the check failed.
+    pub fn from_str_strict(s: &str, opts: ParseOptions) -> Result<Value, ParseError> {
+        let mut deserializer = serde_json::Deserializer::from_str(s);
+        let seed = StrictSeed {
+            opts: &opts,
+            depth: 0,
+        };
+        let value = seed.deserialize(&mut deserializer)?;
+        if opts.reject_trailing {
+            deserializer.end()?;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parses_like_serde_json_by_default() {
+        let value = Value::from_str_strict(
+            r#"{"b": [1, -2, 3.5], "a": {"c": null}}"#,
+            ParseOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(value, json!({"a": {"c": null}, "b": [1, -2, 3.5]}));
+
+        let value = Value::from_str_strict(r#"{"a": 1, "a": 2} trailing"#, ParseOptions::default())
+            .unwrap();
+        assert_eq!(value, json!({"a": 2}));
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        let opts = ParseOptions {
+            reject_duplicate_keys: true,
+            ..ParseOptions::default()
+        };
+        let err = Value::from_str_strict(r#"{"x": {"a": 1, "b": 2, "a": 3}}"#, opts).unwrap_err();
+        assert!(matches!(err, ParseError::Syntax(_)));
+        assert!(err.to_string().contains("duplicate key \"a\""), "{}", err);
+        assert!(Value::from_str_strict(r#"{"a": {"a": 1}}"#, opts).is_ok());
+    }
+
+    #[test]
+    fn rejects_trailing_content() {
+        let opts = ParseOptions {
+            reject_trailing: true,
+            ..ParseOptions::default()
+        };
+        assert!(Value::from_str_strict("[1, 2] x", opts).is_err());
+        assert!(Value::from_str_strict("{} {}", opts).is_err());
+        assert_eq!(
+            Value::from_str_strict(" [1, 2] \n", opts).unwrap(),
+            json!([1, 2])
+        );
+    }
+
+    #[test]
+    fn caps_nesting_depth() {
+        let opts = ParseOptions {
+            max_depth: Some(2),
+            ..ParseOptions::default()
+        };
+        assert!(Value::from_str_strict(r#"{"a": [1]}"#, opts).is_ok());
+        assert!(Value::from_str_strict(r#"{"a": [[1]]}"#, opts).is_err());
+        let scalars_only = ParseOptions {
+            max_depth: Some(0),
+            ..opts
+        };
+        assert!(Value::from_str_strict("1", scalars_only).is_ok());
+        assert!(Value::from_str_strict("[]", scalars_only).is_err());
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn parses_numbers_with_arbitrary_precision() {
+        let scalars_only = ParseOptions {
+            max_depth: Some(0),
+            ..ParseOptions::default()
+        };
+        assert_eq!(
+            Value::from_str_strict("1", scalars_only).unwrap(),
+            Value::Number(Number::PosInt(1))
+        );
+        assert_eq!(
+            Value::from_str_strict("1e400", scalars_only).unwrap(),
+            Value::Number(Number::Big("1e400".to_owned()))
+        );
+
+        let opts = ParseOptions {
+            max_depth: Some(1),
+            ..ParseOptions::default()
+        };
+        let value = Value::from_str_strict(r#"{"a": -2, "b": 2.5}"#, opts).unwrap();
+        assert_eq!(value, json!({"a": -2, "b": 2.5}));
+    }
+}
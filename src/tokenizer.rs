@@ -0,0 +1,314 @@
+use crate::{ParseError, Value};
+use serde::de::Error as _;
+use serde_json::Value as JsonValue;
+use std::io::{BufRead, BufReader, Read};
+
+/// A single step through a JSON document, as produced by `Tokenizer`.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    BeginObject,
+    EndObject,
+    BeginArray,
+    EndArray,
+    /// An object key. The entry's value follows as the next token or tokens.
+    Key(String),
+    /// A string, number, boolean or null.
+    Scalar(Value),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Container {
+    Array,
+    Object,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Expect {
+    /// A value, at the top level or after a colon or an array comma.
+    Value,
+    /// The first element of an array, or its end.
+    ValueOrEnd,
+    /// The first key of an object, or its end.
+    KeyOrEnd,
+    /// A key after an object comma.
+    Key,
+    Colon,
+    /// A comma or the end of the innermost container.
+    CommaOrEnd,
+    /// The top-level value is complete, so only whitespace may follow.
+    End,
+}
+
+/// Splits a single JSON document into tokens without building a tree, so a huge array can be
+/// consumed element by element. The structure is checked as tokens are produced: a malformed
+/// document yields every token before the error and then the error. Read errors and syntax errors
+/// both end the stream.
+pub struct Tokenizer<R> {
+    reader: BufReader<R>,
+    stack: Vec<Container>,
+    expect: Expect,
+    offset: usize,
+    scratch: Vec<u8>,
+    done: bool,
+}
+
+impl<R: Read> Tokenizer<R> {
+    pub fn new(reader: R) -> Self {
+        Tokenizer {
+            reader: BufReader::new(reader),
+            stack: Vec::new(),
+            expect: Expect::Value,
+            offset: 0,
+            scratch: Vec::new(),
+            done: false,
+        }
+    }
+
+    /// The number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The number of arrays and objects currently open.
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    fn peek(&mut self) -> Result<Option<u8>, ParseError> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self) {
+        self.reader.consume(1);
+        self.offset += 1;
+    }
+
+    fn skip_whitespace(&mut self) -> Result<Option<u8>, ParseError> {
+        loop {
+            match self.peek()? {
+                Some(b' ') | Some(b'\n') | Some(b'\r') | Some(b'\t') => self.bump(),
+                other => return Ok(other),
+            }
+        }
+    }
+
+    fn error(&self, msg: &str) -> ParseError {
+        let message = format!("{} at byte {}", msg, self.offset);
+        ParseError::Syntax(serde_json::Error::custom(message))
+    }
+
+    /// Reads a string literal, starting from its opening quote.
+    fn read_string(&mut self) -> Result<String, ParseError> {
+        self.scratch.clear();
+        let mut escaped = false;
+        loop {
+            let byte = match self.peek()? {
+                Some(byte) => byte,
+                None => return Err(self.error("unterminated string")),
+            };
+            self.bump();
+            self.scratch.push(byte);
+            if escaped {
+                escaped = false;
+            } else if byte == b'\\' {
+                escaped = true;
+            } else if byte == b'"' && self.scratch.len() > 1 {
+                break;
+            }
+        }
+        Ok(serde_json::from_slice(&self.scratch)?)
+    }
+
+    /// Reads a number, `true`, `false` or `null`.
+    fn read_literal(&mut self) -> Result<Value, ParseError> {
+        self.scratch.clear();
+        while let Some(byte) = self.peek()? {
+            match byte {
+                b'0'..=b'9' | b'a'..=b'z' | b'A'..=b'Z' | b'+' | b'-' | b'.' => {
+                    self.bump();
+                    self.scratch.push(byte);
+                }
+                _ => break,
+            }
+        }
+        if self.scratch.is_empty() {
+            return Err(self.error("expected value"));
+        }
+        Ok(serde_json::from_slice::<JsonValue>(&self.scratch)?.into())
+    }
+
+    fn after_value(&mut self) {
+        self.expect = if self.stack.is_empty() {
+            Expect::End
+        } else {
+            Expect::CommaOrEnd
+        };
+    }
+
+    fn begin_value(&mut self, byte: u8) -> Result<Token, ParseError> {
+        let token = match byte {
+            b'[' => {
+                self.bump();
+                self.stack.push(Container::Array);
+                self.expect = Expect::ValueOrEnd;
+                return Ok(Token::BeginArray);
+            }
+            b'{' => {
+                self.bump();
+                self.stack.push(Container::Object);
+                self.expect = Expect::KeyOrEnd;
+                return Ok(Token::BeginObject);
+            }
+            b'"' => Token::Scalar(Value::String(self.read_string()?)),
+            _ => Token::Scalar(self.read_literal()?),
+        };
+        self.after_value();
+        Ok(token)
+    }
+
+    fn end_container(&mut self, byte: u8) -> Result<Token, ParseError> {
+        let token = match (byte, self.stack.last()) {
+            (b']', Some(Container::Array)) => Token::EndArray,
+            (b'}', Some(Container::Object)) => Token::EndObject,
+            _ => return Err(self.error("unexpected character")),
+        };
+        self.bump();
+        self.stack.pop();
+        self.after_value();
+        Ok(token)
+    }
+
+    fn next_token(&mut self) -> Result<Option<Token>, ParseError> {
+        loop {
+            let byte = match self.skip_whitespace()? {
+                Some(byte) => byte,
+                None if self.expect == Expect::End => return Ok(None),
+                None => return Err(self.error("unexpected end of input")),
+            };
+            let token = match self.expect {
+                Expect::End => return Err(self.error("trailing characters")),
+                Expect::Colon if byte == b':' => {
+                    self.bump();
+                    self.expect = Expect::Value;
+                    continue;
+                }
+                Expect::Colon => return Err(self.error("expected ':'")),
+                Expect::CommaOrEnd if byte == b',' => {
+                    self.bump();
+                    self.expect = match self.stack.last() {
+                        Some(Container::Object) => Expect::Key,
+                        _ => Expect::Value,
+                    };
+                    continue;
+                }
+                Expect::KeyOrEnd | Expect::Key if byte == b'"' => {
+                    let key = self.read_string()?;
+                    self.expect = Expect::Colon;
+                    Token::Key(key)
+                }
+                Expect::Key => return Err(self.error("expected key")),
+                Expect::ValueOrEnd if byte == b']' => self.end_container(byte)?,
+                Expect::CommaOrEnd | Expect::KeyOrEnd => self.end_container(byte)?,
+                Expect::Value | Expect::ValueOrEnd => self.begin_value(byte)?,
+            };
+            return Ok(Some(token));
+        }
+    }
+}
+
+impl<R: Read> Iterator for Tokenizer<R> {
+    type Item = Result<Token, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        match self.next_token() {
+            Ok(Some(token)) => Some(Ok(token)),
+            Ok(None) => {
+                self.done = true;
+                None
+            }
+            Err(e) => {
+                self.done = true;
+                Some(Err(e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokenize(input: &str) -> Vec<Result<Token, ParseError>> {
+        Tokenizer::new(input.as_bytes()).collect()
+    }
+
+    #[test]
+    fn tokenizes_document() {
+        let input = r#" {"a": [1, "x\"y", null, []], "b": {"c": -2.5e1}, "d": true} "#;
+        let tokens: Vec<Token> = Tokenizer::new(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::BeginObject,
+                Token::Key("a".to_owned()),
+                Token::BeginArray,
+                Token::Scalar(Value::from(1u64)),
+                Token::Scalar(Value::from("x\"y")),
+                Token::Scalar(Value::Null),
+                Token::BeginArray,
+                Token::EndArray,
+                Token::EndArray,
+                Token::Key("b".to_owned()),
+                Token::BeginObject,
+                Token::Key("c".to_owned()),
+                Token::Scalar(Value::from(-25.0)),
+                Token::EndObject,
+                Token::Key("d".to_owned()),
+                Token::Scalar(Value::Bool(true)),
+                Token::EndObject,
+            ]
+        );
+    }
+
+    #[test]
+    fn tokenizes_scalar_root() {
+        let tokens: Vec<Token> = Tokenizer::new(&b"\"\\u00e9\""[..])
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(tokens, vec![Token::Scalar(Value::from("\u{e9}"))]);
+    }
+
+    #[test]
+    fn stops_at_first_error() {
+        for input in &[
+            "[1 2]",
+            "{\"a\" 1}",
+            "[1,]",
+            "{\"a\":1,}",
+            "[}",
+            "[1",
+            "1 2",
+            "",
+            "tru",
+        ] {
+            let tokens = tokenize(input);
+            assert!(
+                matches!(tokens.last(), Some(Err(ParseError::Syntax(_)))),
+                "{} was accepted",
+                input
+            );
+            assert_eq!(tokens.iter().filter(|token| token.is_err()).count(), 1);
+        }
+        let tokens = tokenize("[1 2]");
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            tokens[1].as_ref().unwrap(),
+            &Token::Scalar(Value::from(1u64))
+        );
+    }
+}
@@ -0,0 +1,448 @@
+//! A small subset of jq for reshaping values.
+//!
+//! ```text
+//! program := expr
+//! expr    := path | object | array | string | number | "true" | "false" | "null"
+//! path    := "." [ident] suffix*
+//! suffix  := "." ident | "[" "]" | "[" integer "]"
+//! object  := "{" [field ("," field)*] "}"
+//! field   := (ident | string) [":" expr]
+//! array   := "[" [expr ("," expr)*] "]"
+//! ```
+//!
+//! Every expression produces a stream of values. `.a` looks up a key, `.[n]` an array element and
+//! `.[]` yields every element of an array or every value of an object. Looking up a key or an index
+//! on `null`, or one that is absent, yields `null`. An array collects the streams of its elements,
+//! while an object is built once for every combination of its field values. A field without a value
+//! such as `{name}` is shorthand for `{name: .name}`. The program as a whole must produce exactly
+//! one value.
+
+use crate::{Object, Value};
+use librrb::Vector;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TransformError {
+    /// The program could not be parsed, `position` is a byte offset into the program.
+    Syntax { position: usize, message: String },
+    /// A step of a path was applied to a value it cannot index.
+    Type(String),
+    /// The program produced some number of values other than one.
+    Cardinality(usize),
+}
+
+impl fmt::Display for TransformError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TransformError::Syntax { position, message } => {
+                write!(formatter, "syntax error at {}: {}", position, message)
+            }
+            TransformError::Type(message) => write!(formatter, "type error: {}", message),
+            TransformError::Cardinality(count) => {
+                write!(formatter, "expected exactly one result, found {}", count)
+            }
+        }
+    }
+}
+
+impl Error for TransformError {}
+
+#[derive(Clone, Debug)]
+enum Step {
+    Field(String),
+    Index(usize),
+    Iterate,
+}
+
+#[derive(Clone, Debug)]
+enum Expr {
+    Literal(Value),
+    Path(Vec<Step>),
+    Array(Vec<Expr>),
+    Object(Vec<(String, Expr)>),
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_continue(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Parser { src, pos: 0 }
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, TransformError> {
+        Err(TransformError::Syntax {
+            position: self.pos,
+            message: message.to_owned(),
+        })
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(c) = self.peek() {
+            if !c.is_whitespace() {
+                break;
+            }
+            self.bump();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), TransformError> {
+        if self.eat(expected) {
+            Ok(())
+        } else {
+            self.error(&format!("expected '{}'", expected))
+        }
+    }
+
+    fn parse_program(mut self) -> Result<Expr, TransformError> {
+        let expr = self.parse_expr()?;
+        self.skip_whitespace();
+        if self.pos < self.src.len() {
+            return self.error("unexpected trailing input");
+        }
+        Ok(expr)
+    }
+
+    fn parse_expr(&mut self) -> Result<Expr, TransformError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('.') => self.parse_path(),
+            Some('{') => self.parse_object(),
+            Some('[') => self.parse_array(),
+            Some('"') => Ok(Expr::Literal(Value::String(self.parse_string()?))),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.parse_number(),
+            Some(c) if is_ident_start(c) => {
+                let start = self.pos;
+                match self.parse_ident().as_str() {
+                    "null" => Ok(Expr::Literal(Value::Null)),
+                    "true" => Ok(Expr::Literal(Value::Bool(true))),
+                    "false" => Ok(Expr::Literal(Value::Bool(false))),
+                    _ => {
+                        self.pos = start;
+                        self.error("unknown identifier")
+                    }
+                }
+            }
+            Some(_) => self.error("expected an expression"),
+            None => self.error("unexpected end of program"),
+        }
+    }
+
+    fn parse_ident(&mut self) -> String {
+        let src = self.src;
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !is_ident_continue(c) {
+                break;
+            }
+            self.bump();
+        }
+        src[start..self.pos].to_owned()
+    }
+
+    fn parse_path(&mut self) -> Result<Expr, TransformError> {
+        let src = self.src;
+        let mut steps = Vec::new();
+        self.bump();
+        if self.peek().map_or(false, is_ident_start) {
+            steps.push(Step::Field(self.parse_ident()));
+        }
+        loop {
+            match self.peek() {
+                Some('.') => {
+                    self.bump();
+                    if !self.peek().map_or(false, is_ident_start) {
+                        return self.error("expected a field name");
+                    }
+                    steps.push(Step::Field(self.parse_ident()));
+                }
+                Some('[') => {
+                    self.bump();
+                    if self.eat(']') {
+                        steps.push(Step::Iterate);
+                        continue;
+                    }
+                    self.skip_whitespace();
+                    let start = self.pos;
+                    while self.peek().map_or(false, |c| c.is_ascii_digit()) {
+                        self.bump();
+                    }
+                    match src[start..self.pos].parse::<usize>() {
+                        Ok(idx) => steps.push(Step::Index(idx)),
+                        Err(_) => {
+                            self.pos = start;
+                            return self.error("expected an array index");
+                        }
+                    }
+                    self.expect(']')?;
+                }
+                _ => break,
+            }
+        }
+        Ok(Expr::Path(steps))
+    }
+
+    fn parse_object(&mut self) -> Result<Expr, TransformError> {
+        self.bump();
+        let mut fields = Vec::new();
+        if self.eat('}') {
+            return Ok(Expr::Object(fields));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = match self.peek() {
+                Some('"') => self.parse_string()?,
+                Some(c) if is_ident_start(c) => self.parse_ident(),
+                _ => return self.error("expected an object key"),
+            };
+            let value = if self.eat(':') {
+                self.parse_expr()?
+            } else {
+                Expr::Path(vec![Step::Field(key.clone())])
+            };
+            fields.push((key, value));
+            if self.eat('}') {
+                return Ok(Expr::Object(fields));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_array(&mut self) -> Result<Expr, TransformError> {
+        self.bump();
+        let mut items = Vec::new();
+        if self.eat(']') {
+            return Ok(Expr::Array(items));
+        }
+        loop {
+            items.push(self.parse_expr()?);
+            if self.eat(']') {
+                return Ok(Expr::Array(items));
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String, TransformError> {
+        let src = self.src;
+        let start = self.pos;
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(_) => {}
+                None => {
+                    self.pos = start;
+                    return self.error("unterminated string");
+                }
+            }
+        }
+        match serde_json::from_str(&src[start..self.pos]) {
+            Ok(s) => Ok(s),
+            Err(_) => {
+                self.pos = start;
+                self.error("invalid string literal")
+            }
+        }
+    }
+
+    fn parse_number(&mut self) -> Result<Expr, TransformError> {
+        let src = self.src;
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !(c.is_ascii_digit() || "+-.eE".contains(c)) {
+                break;
+            }
+            self.bump();
+        }
+        match serde_json::from_str::<serde_json::Number>(&src[start..self.pos]) {
+            Ok(n) => Ok(Expr::Literal(Value::Number(n.into()))),
+            Err(_) => {
+                self.pos = start;
+                self.error("invalid number literal")
+            }
+        }
+    }
+}
+
+fn apply_step<'v>(
+    step: &Step,
+    value: &'v Value,
+    out: &mut Vec<&'v Value>,
+) -> Result<(), TransformError> {
+    match (step, value) {
+        (Step::Field(_), Value::Null) | (Step::Index(_), Value::Null) => out.push(&Value::Null),
+        (Step::Field(key), Value::Object(obj)) => {
+            out.push(obj.get(key.as_str()).unwrap_or(&Value::Null))
+        }
+        (Step::Index(idx), Value::Array(arr)) => out.push(arr.get(*idx).unwrap_or(&Value::Null)),
+        (Step::Iterate, Value::Array(arr)) => out.extend(arr.iter()),
+        (Step::Iterate, Value::Object(obj)) => out.extend(obj.values()),
+        (Step::Field(key), _) => {
+            return Err(TransformError::Type(format!(
+                "cannot index {} with \"{}\"",
+                value.type_name(),
+                key
+            )))
+        }
+        (Step::Index(idx), _) => {
+            return Err(TransformError::Type(format!(
+                "cannot index {} with {}",
+                value.type_name(),
+                idx
+            )))
+        }
+        (Step::Iterate, _) => {
+            return Err(TransformError::Type(format!(
+                "cannot iterate over {}",
+                value.type_name()
+            )))
+        }
+    }
+    Ok(())
+}
+
+fn eval(expr: &Expr, input: &Value) -> Result<Vec<Value>, TransformError> {
+    match expr {
+        Expr::Literal(value) => Ok(vec![value.clone()]),
+        Expr::Path(steps) => {
+            let mut current = vec![input];
+            for step in steps {
+                let mut next = Vec::new();
+                for value in current {
+                    apply_step(step, value, &mut next)?;
+                }
+                current = next;
+            }
+            Ok(current.into_iter().cloned().collect())
+        }
+        Expr::Array(items) => {
+            let mut arr = Vector::new();
+            for item in items {
+                for value in eval(item, input)? {
+                    arr.push_back(value);
+                }
+            }
+            Ok(vec![Value::Array(arr)])
+        }
+        Expr::Object(fields) => {
+            let mut partials = vec![Object::new()];
+            for (key, field) in fields {
+                let values = eval(field, input)?;
+                let mut next = Vec::with_capacity(partials.len() * values.len());
+                for partial in &partials {
+                    for value in &values {
+                        let mut obj = partial.clone();
+                        obj.insert(key.clone(), value.clone());
+                        next.push(obj);
+                    }
+                }
+                partials = next;
+            }
+            Ok(partials.into_iter().map(Value::Object).collect())
+        }
+    }
+}
+
+impl Value {
+    /// Runs a program written in the jq subset described in the `transform` module against this
+    /// value.
+    pub fn transform(&self, program: &str) -> Result<Value, TransformError> {
+        let expr = Parser::new(program).parse_program()?;
+        let mut results = eval(&expr, self)?;
+        if results.len() == 1 {
+            Ok(results.pop().unwrap())
+        } else {
+            Err(TransformError::Cardinality(results.len()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn projects_fields() {
+        let value: Value = json!({"user": {"name": "ann", "age": 31}, "extra": true}).into();
+        let result = value
+            .transform("{name: .user.name, \"years\": .user.age}")
+            .unwrap();
+        let expected: Value = json!({"name": "ann", "years": 31}).into();
+        assert_eq!(result, expected);
+
+        let result = value.transform("{extra, missing: .user.email}").unwrap();
+        let expected: Value = json!({"extra": true, "missing": null}).into();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn maps_arrays() {
+        let value: Value = json!({
+            "user": {"name": "ann"},
+            "items": [{"id": 1}, {"id": 2}, {"id": 3}]
+        })
+        .into();
+        let result = value
+            .transform("{name: .user.name, ids: [.items[].id], first: .items[0].id}")
+            .unwrap();
+        let expected: Value = json!({"name": "ann", "ids": [1, 2, 3], "first": 1}).into();
+        assert_eq!(result, expected);
+
+        let result = value.transform("[.user.name, -1.5, null]").unwrap();
+        let expected: Value = json!(["ann", -1.5, null]).into();
+        assert_eq!(result, expected);
+    }
+
+    #[test]
+    fn reports_errors() {
+        let value: Value = json!({"items": [1, 2]}).into();
+        match value.transform("{name: .user.name") {
+            Err(TransformError::Syntax { position, .. }) => assert_eq!(position, 17),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            value.transform("{a: .items.b}"),
+            Err(TransformError::Type(_))
+        ));
+        assert_eq!(
+            value.transform(".items[]"),
+            Err(TransformError::Cardinality(2))
+        );
+    }
+}
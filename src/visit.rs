@@ -0,0 +1,256 @@
+use crate::pointer::escape;
+use crate::{Iter, Number, Value};
+use librrb::Iter as VIter;
+use std::fmt::Write;
+use std::iter::Enumerate;
+
+/// Callbacks for `Value::visit`. Every method does nothing by default. Object entries are reported
+/// as a `visit_key` followed by the visit of the entry's value.
+pub trait Visitor {
+    fn visit_null(&mut self) {}
+
+    fn visit_bool(&mut self, _value: bool) {}
+
+    fn visit_number(&mut self, _value: &Number) {}
+
+    fn visit_string(&mut self, _value: &str) {}
+
+    fn enter_array(&mut self, _len: usize) {}
+
+    fn exit_array(&mut self) {}
+
+    fn enter_object(&mut self, _len: usize) {}
+
+    fn visit_key(&mut self, _key: &str) {}
+
+    fn exit_object(&mut self) {}
+}
+
+enum Frame<'a> {
+    Array(VIter<'a, Value>),
+    Object(Iter<'a>),
+}
+
+/// A container being walked by `Value::for_each_node`, with the length of its own pointer.
+enum PathFrame<'a> {
+    Array(Enumerate<VIter<'a, Value>>, usize),
+    Object(Iter<'a>, usize),
+}
+
+impl Value {
+    /// Walks the whole document in order, calling into the visitor for every node. The traversal
+    /// keeps its own stack so deeply nested documents do not overflow the call stack.
+    pub fn visit<V: Visitor>(&self, visitor: &mut V) {
+        let mut stack = Vec::new();
+        let mut next = Some(self);
+        loop {
+            if let Some(value) = next.take() {
+                match value {
+                    Value::Null => visitor.visit_null(),
+                    Value::Bool(b) => visitor.visit_bool(*b),
+                    Value::Number(n) => visitor.visit_number(n),
+                    Value::String(s) => visitor.visit_string(s),
+                    Value::Array(arr) => {
+                        visitor.enter_array(arr.len());
+                        stack.push(Frame::Array(arr.iter()));
+                    }
+                    Value::Object(obj) => {
+                        visitor.enter_object(obj.len());
+                        stack.push(Frame::Object(obj.iter()));
+                    }
+                }
+            }
+            match stack.last_mut() {
+                None => break,
+                Some(Frame::Array(iter)) => match iter.next() {
+                    Some(value) => next = Some(value),
+                    None => {
+                        stack.pop();
+                        visitor.exit_array();
+                    }
+                },
+                Some(Frame::Object(iter)) => match iter.next() {
+                    Some((key, value)) => {
+                        visitor.visit_key(key);
+                        next = Some(value);
+                    }
+                    None => {
+                        stack.pop();
+                        visitor.exit_object();
+                    }
+                },
+            }
+        }
+    }
+
+    /// Calls `f` with every node of the document and its JSON Pointer, in document order. Parents
+    /// come before their children. The pointer is kept in one buffer that grows and
+    /// shrinks as the walk moves through the document, and the walk keeps its own stack.
+    pub fn for_each_node<F>(&self, mut f: F)
+    where
+        F: FnMut(&str, &Value),
+    {
+        let mut path = String::new();
+        let mut stack = Vec::new();
+        let mut next = Some(self);
+        loop {
+            if let Some(value) = next.take() {
+                f(&path, value);
+                match value {
+                    Value::Array(arr) => {
+                        stack.push(PathFrame::Array(arr.iter().enumerate(), path.len()))
+                    }
+                    Value::Object(obj) => stack.push(PathFrame::Object(obj.iter(), path.len())),
+                    _ => {}
+                }
+            }
+            match stack.last_mut() {
+                None => break,
+                Some(PathFrame::Array(iter, len)) => {
+                    path.truncate(*len);
+                    match iter.next() {
+                        Some((idx, value)) => {
+                            write!(path, "/{}", idx).unwrap();
+                            next = Some(value);
+                        }
+                        None => {
+                            stack.pop();
+                        }
+                    }
+                }
+                Some(PathFrame::Object(iter, len)) => {
+                    path.truncate(*len);
+                    match iter.next() {
+                        Some((key, value)) => {
+                            path.push('/');
+                            if key.contains(|c: char| c == '~' || c == '/') {
+                                path.push_str(&escape(key));
+                            } else {
+                                path.push_str(key);
+                            }
+                            next = Some(value);
+                        }
+                        None => {
+                            stack.pop();
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Default)]
+    struct Counter {
+        scalars: usize,
+        keys: Vec<String>,
+        depth: usize,
+        max_depth: usize,
+    }
+
+    impl Counter {
+        fn enter(&mut self) {
+            self.depth += 1;
+            self.max_depth = self.max_depth.max(self.depth);
+        }
+    }
+
+    impl Visitor for Counter {
+        fn visit_null(&mut self) {
+            self.scalars += 1;
+        }
+
+        fn visit_bool(&mut self, _value: bool) {
+            self.scalars += 1;
+        }
+
+        fn visit_number(&mut self, _value: &Number) {
+            self.scalars += 1;
+        }
+
+        fn visit_string(&mut self, _value: &str) {
+            self.scalars += 1;
+        }
+
+        fn enter_array(&mut self, _len: usize) {
+            self.enter();
+        }
+
+        fn exit_array(&mut self) {
+            self.depth -= 1;
+        }
+
+        fn enter_object(&mut self, _len: usize) {
+            self.enter();
+        }
+
+        fn visit_key(&mut self, key: &str) {
+            self.keys.push(key.to_owned());
+        }
+
+        fn exit_object(&mut self) {
+            self.depth -= 1;
+        }
+    }
+
+    #[test]
+    fn counts_scalars() {
+        let value: Value = json!({
+            "a": [1, "two", null, [true, {}]],
+            "b": {"c": false},
+            "d": []
+        })
+        .into();
+        let mut counter = Counter::default();
+        value.visit(&mut counter);
+        assert_eq!(counter.scalars, 5);
+        assert_eq!(counter.keys, vec!["a", "b", "c", "d"]);
+        assert_eq!(counter.depth, 0);
+        assert_eq!(counter.max_depth, 4);
+    }
+
+    #[test]
+    fn visits_scalar_root() {
+        let mut counter = Counter::default();
+        Value::Bool(true).visit(&mut counter);
+        assert_eq!(counter.scalars, 1);
+        assert_eq!(counter.max_depth, 0);
+    }
+
+    #[test]
+    fn passes_pointer_to_every_node() {
+        let value: Value = json!({"a": [1, {"b": null}], "c~/": "x", "d": {}}).into();
+        let mut nodes = Vec::new();
+        value.for_each_node(|path, node| {
+            nodes.push((path.to_owned(), !node.is_array() && !node.is_object()));
+        });
+        let expected = vec![
+            ("", false),
+            ("/a", false),
+            ("/a/0", true),
+            ("/a/1", false),
+            ("/a/1/b", true),
+            ("/c~0~1", true),
+            ("/d", false),
+        ];
+        let expected: Vec<(String, bool)> = expected
+            .into_iter()
+            .map(|(path, scalar)| (path.to_owned(), scalar))
+            .collect();
+        assert_eq!(nodes, expected);
+
+        let mut paths = Vec::new();
+        value.for_each_node(|path, _| paths.push(path.to_owned()));
+        let found: Vec<String> = value
+            .find_all(|_| true)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert_eq!(paths, found);
+    }
+}
@@ -0,0 +1,146 @@
+//! YAML conversion. YAML allows keys that are not strings, while objects require them: scalar keys
+//! are converted to their textual form, so `1: a` becomes `{"1": "a"}`, and sequence or mapping
+//! keys are rejected. A mapping with duplicate keys is rejected, including keys that only become
+//! equal once stringified. Tags are ignored in favour of the tagged value.
+
+use crate::{Number, Object, Value};
+use librrb::Vector;
+use serde_yaml::Value as YamlValue;
+use std::error::Error;
+use std::fmt::{self, Display};
+
+#[derive(Debug)]
+pub enum YamlError {
+    Yaml(serde_yaml::Error),
+    /// A mapping key that is a sequence or a mapping.
+    InvalidKey,
+    /// A key that appears more than once in the same mapping.
+    DuplicateKey(String),
+    /// A number that is not finite.
+    InvalidNumber,
+}
+
+impl fmt::Display for YamlError {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            YamlError::Yaml(e) => Display::fmt(e, formatter),
+            YamlError::InvalidKey => write!(formatter, "mapping keys must be scalars"),
+            YamlError::DuplicateKey(key) => write!(formatter, "duplicate mapping key \"{}\"", key),
+            YamlError::InvalidNumber => write!(formatter, "numbers must be finite"),
+        }
+    }
+}
+
+impl Error for YamlError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            YamlError::Yaml(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_yaml::Error> for YamlError {
+    fn from(e: serde_yaml::Error) -> Self {
+        YamlError::Yaml(e)
+    }
+}
+
+fn convert_number(n: &serde_yaml::Number) -> Result<Number, YamlError> {
+    if let Some(u) = n.as_u64() {
+        Ok(Number::PosInt(u))
+    } else if let Some(i) = n.as_i64() {
+        Ok(Number::from(i))
+    } else {
+        n.as_f64()
+            .and_then(Number::from_f64)
+            .ok_or(YamlError::InvalidNumber)
+    }
+}
+
+fn convert_key(key: YamlValue) -> Result<String, YamlError> {
+    match key {
+        YamlValue::String(s) => Ok(s),
+        YamlValue::Null => Ok("null".to_owned()),
+        YamlValue::Bool(b) => Ok(b.to_string()),
+        YamlValue::Number(n) => Ok(n.to_string()),
+        YamlValue::Tagged(tagged) => convert_key(tagged.value),
+        YamlValue::Sequence(_) | YamlValue::Mapping(_) => Err(YamlError::InvalidKey),
+    }
+}
+
+fn convert(value: YamlValue) -> Result<Value, YamlError> {
+    Ok(match value {
+        YamlValue::Null => Value::Null,
+        YamlValue::Bool(b) => Value::Bool(b),
+        YamlValue::Number(n) => Value::Number(convert_number(&n)?),
+        YamlValue::String(s) => Value::String(s),
+        YamlValue::Sequence(items) => {
+            let mut arr = Vector::new();
+            for item in items {
+                arr.push_back(convert(item)?);
+            }
+            Value::Array(arr)
+        }
+        YamlValue::Mapping(mapping) => {
+            let mut obj = Object::new();
+            for (k, v) in mapping {
+                let key = convert_key(k)?;
+                if obj.contains_key(&key) {
+                    return Err(YamlError::DuplicateKey(key));
+                }
+                obj.insert(key, convert(v)?);
+            }
+            Value::Object(obj)
+        }
+        YamlValue::Tagged(tagged) => convert(tagged.value)?,
+    })
+}
+
+impl Value {
+    pub fn from_yaml(s: &str) -> Result<Value, YamlError> {
+        convert(serde_yaml::from_str(s)?)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, YamlError> {
+        Ok(serde_yaml::to_string(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn round_trips_nested_config() {
+        let value: Value = json!({
+            "server": {"host": "localhost", "port": 8080, "tls": false},
+            "limits": [1.5, -2, null],
+            "name": "a: b"
+        })
+        .into();
+        let yaml = value.to_yaml().unwrap();
+        assert_eq!(Value::from_yaml(&yaml).unwrap(), value);
+    }
+
+    #[test]
+    fn stringifies_scalar_keys() {
+        let value = Value::from_yaml("1: a\ntrue: b\nc: [x]\n").unwrap();
+        let expected: Value = json!({"1": "a", "true": "b", "c": ["x"]}).into();
+        assert_eq!(value, expected);
+        assert!(matches!(
+            Value::from_yaml("[a]: b\n"),
+            Err(YamlError::InvalidKey)
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_keys() {
+        assert!(Value::from_yaml("a: 1\nb: 2\na: 3\n").is_err());
+        assert!(matches!(
+            Value::from_yaml("1: a\n\"1\": b\n"),
+            Err(YamlError::DuplicateKey(ref key)) if key == "1"
+        ));
+    }
+}
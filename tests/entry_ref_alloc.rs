@@ -0,0 +1,50 @@
+//! Counts allocations made by `Object::entry_ref`. The counting allocator replaces the global
+//! allocator for the whole binary, so it lives in its own test binary.
+
+use persistent_json::{Entry, Value};
+use serde_json::json;
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: std::cell::Cell<usize> = std::cell::Cell::new(0);
+}
+
+/// Counts allocations per thread, so tests running in parallel do not disturb each other.
+unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+        std::alloc::System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+        std::alloc::System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+#[test]
+fn object_entry_ref_allocates_only_when_vacant() {
+    let value: Value = json!({"a": 1, "b": 2}).into();
+    let mut obj = value.as_object().unwrap().clone();
+
+    let before = allocations();
+    for _ in 0..100 {
+        match obj.entry_ref("a") {
+            Entry::Occupied(entry) => assert_eq!(entry.key(), "a"),
+            Entry::Vacant(_) => panic!("a is present"),
+        }
+    }
+    assert_eq!(allocations(), before);
+
+    *obj.entry_ref("c").or_insert(Value::Null) = Value::Bool(true);
+    *obj.entry_ref("a").or_insert(Value::Null) = Value::Number(3.into());
+    let expected: Value = json!({"a": 3, "b": 2, "c": true}).into();
+    assert_eq!(Value::Object(obj), expected);
+}